@@ -0,0 +1,170 @@
+//! Append-only audit trail of authentication and content changes.
+
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use entity::{audit_log, user};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use service::sea_orm::{prelude::Uuid, ActiveValue::Set, DatabaseConnection};
+
+use crate::{auth::Claims, client::ClientIp, config::Config};
+
+/// Who performed an action. The name is kept so records stay readable after
+/// the user is deleted.
+pub struct Actor {
+    pub id: Uuid,
+    pub name: String,
+}
+
+impl From<&Claims> for Actor {
+    fn from(claims: &Claims) -> Self {
+        Self {
+            id: claims.user_id,
+            name: claims.name.clone(),
+        }
+    }
+}
+
+impl From<&user::Model> for Actor {
+    fn from(user: &user::Model) -> Self {
+        Self {
+            id: user.id,
+            name: user.username.clone(),
+        }
+    }
+}
+
+/// Database handle plus the client details every record carries.
+pub struct Audit {
+    db: DatabaseConnection,
+    ip: String,
+    user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Audit
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+    DatabaseConnection: FromRef<S>,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ClientIp(ip) = ClientIp::from_request_parts(parts, state).await?;
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(Self {
+            db: DatabaseConnection::from_ref(state),
+            ip: ip.to_string(),
+            user_agent,
+        })
+    }
+}
+
+impl Audit {
+    /// Appends a record. A failed write fails the request so that no audited
+    /// change goes unrecorded without the caller noticing.
+    pub async fn record(
+        &self,
+        actor: Option<Actor>,
+        action: &str,
+        target_type: &str,
+        target_id: impl ToString,
+        diff: Option<Value>,
+    ) -> crate::response::Result<()> {
+        let (actor_id, actor_name) = match actor {
+            Some(actor) => (Some(actor.id), Some(actor.name)),
+            None => (None, None),
+        };
+        let log = audit_log::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            actor_id: Set(actor_id),
+            actor_name: Set(actor_name),
+            action: Set(action.to_string()),
+            target_type: Set(target_type.to_string()),
+            target_id: Set(Some(target_id.to_string())),
+            ip: Set(Some(self.ip.clone())),
+            user_agent: Set(self.user_agent.clone()),
+            diff: Set(diff),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        };
+        service::mutation::Mutation::create_audit_log(&self.db, log)
+            .await
+            .map_err(|e| {
+                tracing::error!(action, error = %e, "failed to write audit log");
+                e
+            })?;
+        Ok(())
+    }
+}
+
+/// `{"before": ..., "after": ...}` limited to the fields that changed; either
+/// side may be absent for creations and deletions.
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> Option<Value> {
+    let before = before.and_then(|v| serde_json::to_value(v).ok());
+    let after = after.and_then(|v| serde_json::to_value(v).ok());
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let (mut old, mut new) = (Map::new(), Map::new());
+            for (key, value) in &after {
+                if before.get(key) != Some(value) {
+                    old.insert(key.clone(), before.get(key).cloned().unwrap_or(Value::Null));
+                    new.insert(key.clone(), value.clone());
+                }
+            }
+            Some(json!({ "before": old, "after": new }))
+        }
+        (None, None) => None,
+        (before, after) => Some(json!({ "before": before, "after": after })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_keeps_only_changed_fields() {
+        let before = json!({ "title": "a", "content": "x", "tags": [1, 2] });
+        let after = json!({ "title": "b", "content": "x", "tags": [1, 2], "summary": "s" });
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            Some(json!({
+                "before": { "title": "a", "summary": null },
+                "after": { "title": "b", "summary": "s" },
+            }))
+        );
+    }
+
+    #[test]
+    fn unchanged_update_is_empty() {
+        let value = json!({ "title": "a" });
+        assert_eq!(
+            diff(Some(&value), Some(&value)),
+            Some(json!({ "before": {}, "after": {} }))
+        );
+    }
+
+    #[test]
+    fn creation_and_deletion_keep_whole_values() {
+        let value = json!({ "title": "a" });
+        assert_eq!(
+            diff(None, Some(&value)),
+            Some(json!({ "before": null, "after": { "title": "a" } }))
+        );
+        assert_eq!(
+            diff(Some(&value), None),
+            Some(json!({ "before": { "title": "a" }, "after": null }))
+        );
+        assert_eq!(diff::<Value>(None, None), None);
+    }
+}
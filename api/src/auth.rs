@@ -1,56 +1,575 @@
-use axum::{async_trait, extract::FromRequestParts, http::request::Parts, RequestPartsExt};
+use std::{collections::HashMap, fs, marker::PhantomData, sync::Arc};
+
+use anyhow::{anyhow, bail, Context};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+    RequestPartsExt,
+};
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
-use jsonwebtoken::{decode, DecodingKey, EncodingKey, Validation};
-use serde::{Deserialize, Serialize};
-use service::sea_orm::prelude::Uuid;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use entity::{sea_orm_active_enums::RoleEnum, user, user_token};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{ErrorKind, Result as JwtResult},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::{
+    pkcs8::{spki::SubjectPublicKeyInfoRef, DecodePublicKey, Document},
+    traits::PublicKeyParts,
+    RsaPublicKey,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use service::sea_orm::{prelude::Uuid, DatabaseConnection};
+use tower_cookies::Cookies;
+use utoipa::ToSchema;
+
+use crate::{
+    config::{Config, JwtConfig, JwtKeyConfig},
+    cookie,
+    error::CustomError,
+    response::Result,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub user_id: Uuid,
     pub name: String,
+    pub role: RoleEnum,
+    /// Session the token was issued for, checked against revocation on every
+    /// request. For personal access tokens this is the token id.
+    pub sid: Uuid,
     pub exp: i64,
+    /// Scopes of a personal access token; `None` for session tokens, which
+    /// are not restricted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: RoleEnum) -> bool {
+        self.role >= role
+    }
+}
+
+/// Prefix telling personal access tokens apart from JWTs.
+pub const ACCESS_TOKEN_PREFIX: &str = "blog_pat_";
+
+/// Permission a personal access token can be granted.
+///
+/// Personal access tokens are rejected everywhere except on routes layered
+/// with `Extension(scope)`, and only if the token holds that scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    BlogWrite,
+    Upload,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 2] = [Scope::BlogWrite, Scope::Upload];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::BlogWrite => "blog:write",
+            Scope::Upload => "upload",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|i| i.as_str() == value)
+    }
 }
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Access token lifetime in seconds.
+    pub expires_in: i64,
+}
+
+/// Returned instead of tokens when the account has TOTP enabled; exchange it
+/// together with a code at `/user/login/mfa`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaChallenge {
+    pub mfa_token: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Tokens(TokenPair),
+    MfaRequired(MfaChallenge),
+}
+
+/// Issues the short-lived token that lets a TOTP user finish logging in.
+pub async fn mfa_challenge(
+    db: &DatabaseConnection,
+    config: &Config,
+    keys: &Keys,
+    user_id: Uuid,
+) -> Result<MfaChallenge> {
+    let mfa_token = issue_action_token(
+        db,
+        keys,
+        user_id,
+        user_token::purpose::MFA,
+        config.mfa_token_ttl,
+    )
+    .await?;
+    Ok(MfaChallenge {
+        mfa_token,
+        expires_in: config.mfa_token_ttl,
+    })
+}
+
+/// Starts a new session for `user` and returns its first token pair.
+pub async fn issue_tokens(
+    db: &DatabaseConnection,
+    config: &Config,
+    keys: &Keys,
+    user: &user::Model,
+) -> Result<TokenPair> {
+    // 先拒绝已停用的账号，避免留下无法使用的会话
+    ensure_enabled(user)?;
+    let secret = service::token::generate();
+    let expires_at =
+        chrono::Utc::now().naive_utc() + chrono::Duration::seconds(config.refresh_token_ttl);
+    let session = service::mutation::Mutation::create_session(
+        db,
+        user.id,
+        service::token::hash(&secret),
+        expires_at,
+    )
+    .await?;
+    token_pair(config, keys, user, session.id, &secret)
+}
+
+/// Exchanges a refresh token for a new pair, invalidating the presented one.
+///
+/// Presenting a refresh token that was already rotated means it leaked, so the
+/// whole session is revoked.
+pub async fn refresh_tokens(
+    db: &DatabaseConnection,
+    config: &Config,
+    keys: &Keys,
+    refresh_token: &str,
+) -> Result<TokenPair> {
+    let (sid, secret) = refresh_token
+        .split_once('.')
+        .ok_or(CustomError::Unauthorized("Invalid refresh token"))?;
+    let sid =
+        Uuid::parse_str(sid).map_err(|_| CustomError::Unauthorized("Invalid refresh token"))?;
+    let session = service::query::Query::get_session(db, sid)
+        .await?
+        .ok_or(CustomError::Unauthorized("Invalid refresh token"))?;
+    let now = chrono::Utc::now().naive_utc();
+    if session.revoked_at.is_some() || session.expires_at <= now {
+        return Err(CustomError::Unauthorized("Session expired"));
+    }
+    let old_hash = service::token::hash(secret);
+    let new_secret = service::token::generate();
+    let rotated = service::mutation::Mutation::rotate_session(
+        db,
+        sid,
+        &old_hash,
+        service::token::hash(&new_secret),
+        now + chrono::Duration::seconds(config.refresh_token_ttl),
+    )
+    .await?;
+    if !rotated {
+        service::mutation::Mutation::revoke_session(db, sid).await?;
+        return Err(CustomError::Unauthorized("Refresh token reused"));
+    }
+    let user = service::query::Query::get_user(db, session.user_id)
+        .await?
+        .ok_or(CustomError::Unauthorized("Invalid refresh token"))?;
+    token_pair(config, keys, &user, sid, &new_secret)
+}
+
+/// Claims of a signed single-use token, e.g. an email verification link.
+/// `jti` refers to a `user_token` row which is marked as used on consumption.
+#[derive(Debug, Serialize, Deserialize)]
+struct ActionClaims {
+    sub: Uuid,
+    jti: Uuid,
+    aud: String,
+    exp: i64,
+}
+
+pub async fn issue_action_token(
+    db: &DatabaseConnection,
+    keys: &Keys,
+    user_id: Uuid,
+    purpose: &str,
+    ttl: i64,
+) -> Result<String> {
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(ttl);
+    let token = service::mutation::Mutation::create_user_token(
+        db,
+        user_id,
+        purpose,
+        expires_at.naive_utc(),
+    )
+    .await?;
+    let claims = ActionClaims {
+        sub: user_id,
+        jti: token.id,
+        aud: purpose.to_string(),
+        exp: expires_at.timestamp(),
+    };
+    Ok(keys.encode(&claims)?)
+}
+
+/// Verifies a token from [`issue_action_token`] without using it up.
+pub fn action_token_user(keys: &Keys, token: &str, purpose: &str) -> Result<Uuid> {
+    Ok(keys.decode_for::<ActionClaims>(token, purpose)?.sub)
+}
+
+/// Verifies and uses up a token from [`issue_action_token`], returning its user id.
+pub async fn consume_action_token(
+    db: &DatabaseConnection,
+    keys: &Keys,
+    token: &str,
+    purpose: &str,
+) -> Result<Uuid> {
+    let claims = keys.decode_for::<ActionClaims>(token, purpose)?;
+    let token = service::mutation::Mutation::consume_user_token(db, claims.jti, purpose)
+        .await?
+        .ok_or(CustomError::Unauthorized("Token has already been used"))?;
+    Ok(token.user_id)
+}
+
+fn ensure_enabled(user: &user::Model) -> Result<()> {
+    if user.disabled_at.is_some() {
+        return Err(CustomError::Unauthorized("Account is disabled"));
+    }
+    Ok(())
+}
+
+fn token_pair(
+    config: &Config,
+    keys: &Keys,
+    user: &user::Model,
+    sid: Uuid,
+    secret: &str,
+) -> Result<TokenPair> {
+    ensure_enabled(user)?;
+    let claims = Claims {
+        user_id: user.id,
+        name: user.username.clone(),
+        role: user.role,
+        sid,
+        exp: chrono::Utc::now().timestamp() + config.access_token_ttl,
+        scopes: None,
+    };
+    Ok(TokenPair {
+        access_token: keys.encode(&claims)?,
+        refresh_token: format!("{sid}.{secret}"),
+        expires_in: config.access_token_ttl,
+    })
+}
+
+/// Signing key plus every key accepted for verification, indexed by `kid`.
 pub struct Keys {
-    pub encoding: EncodingKey,
-    pub decoding: DecodingKey,
+    kid: String,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: HashMap<String, (Algorithm, DecodingKey)>,
+    jwks: JwkSet,
 }
 
 impl Keys {
-    pub fn new(secret: &[u8]) -> Self {
-        Self {
-            encoding: EncodingKey::from_secret(secret),
-            decoding: DecodingKey::from_secret(secret),
+    pub fn from_config(config: &JwtConfig) -> anyhow::Result<Self> {
+        let mut decoding = HashMap::new();
+        let mut jwks = JwkSet { keys: vec![] };
+        let mut signing = None;
+        for key in config.keys.iter() {
+            let (decoding_key, jwk) = load_public(key)
+                .with_context(|| format!("failed to load public key {}", key.kid))?;
+            if key.kid == config.active {
+                let encoding_key = load_private(key)
+                    .with_context(|| format!("failed to load private key {}", key.kid))?;
+                signing = Some((key.alg, encoding_key));
+            }
+            if decoding
+                .insert(key.kid.clone(), (key.alg, decoding_key))
+                .is_some()
+            {
+                bail!("duplicate kid {}", key.kid);
+            }
+            jwks.keys.extend(jwk);
+        }
+        let (algorithm, encoding) =
+            signing.ok_or_else(|| anyhow!("active key {} is not configured", config.active))?;
+        Ok(Self {
+            kid: config.active.clone(),
+            algorithm,
+            encoding,
+            decoding,
+            jwks,
+        })
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> JwtResult<String> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.kid.clone());
+        encode(&header, claims, &self.encoding)
+    }
+
+    /// Decodes an access token. Tokens carrying an `aud` claim are rejected.
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> JwtResult<T> {
+        self.decode_with(token, |_| {})
+    }
+
+    /// Decodes a token that was issued for `audience` only.
+    pub fn decode_for<T: DeserializeOwned>(&self, token: &str, audience: &str) -> JwtResult<T> {
+        self.decode_with(token, |validation| {
+            validation.set_audience(&[audience]);
+            validation.set_required_spec_claims(&["exp", "aud"]);
+        })
+    }
+
+    fn decode_with<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        configure: impl FnOnce(&mut Validation),
+    ) -> JwtResult<T> {
+        let header = decode_header(token)?;
+        let kid = header.kid.ok_or(ErrorKind::InvalidToken)?;
+        // 未知的 kid 无法通过任何已知密钥的校验，按签名错误处理
+        let (algorithm, key) = self.decoding.get(&kid).ok_or(ErrorKind::InvalidSignature)?;
+        if header.alg != *algorithm {
+            return Err(ErrorKind::InvalidAlgorithm.into());
         }
+        let mut validation = Validation::new(*algorithm);
+        configure(&mut validation);
+        Ok(decode::<T>(token, key, &validation)?.claims)
+    }
+
+    /// Public verification keys; shared secrets are never published.
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+fn load_private(key: &JwtKeyConfig) -> anyhow::Result<EncodingKey> {
+    Ok(match key.alg {
+        Algorithm::HS256 => EncodingKey::from_secret(secret(key)?),
+        Algorithm::RS256 => EncodingKey::from_rsa_pem(&read_pem(key.private_key.as_ref())?)?,
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(&read_pem(key.private_key.as_ref())?)?,
+        alg => bail!("unsupported algorithm {alg:?}"),
+    })
+}
+
+fn load_public(key: &JwtKeyConfig) -> anyhow::Result<(DecodingKey, Option<Jwk>)> {
+    if key.alg == Algorithm::HS256 {
+        return Ok((DecodingKey::from_secret(secret(key)?), None));
     }
+    let pem = read_pem(key.public_key.as_ref())?;
+    let pem = std::str::from_utf8(&pem)?;
+    let (decoding, algorithm, parameters) = match key.alg {
+        Algorithm::RS256 => {
+            let public = RsaPublicKey::from_public_key_pem(pem)?;
+            let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(public.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(public.e().to_bytes_be()),
+            });
+            let decoding = DecodingKey::from_rsa_pem(pem.as_bytes())?;
+            (decoding, KeyAlgorithm::RS256, parameters)
+        }
+        Algorithm::EdDSA => {
+            let (_, document) = Document::from_pem(pem)?;
+            let info: SubjectPublicKeyInfoRef = document.decode_msg()?;
+            let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(info.subject_public_key.raw_bytes()),
+            });
+            let decoding = DecodingKey::from_ed_pem(pem.as_bytes())?;
+            (decoding, KeyAlgorithm::EdDSA, parameters)
+        }
+        alg => bail!("unsupported algorithm {alg:?}"),
+    };
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(algorithm),
+            key_id: Some(key.kid.clone()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+    Ok((decoding, Some(jwk)))
+}
+
+fn secret(key: &JwtKeyConfig) -> anyhow::Result<&[u8]> {
+    key.secret
+        .as_deref()
+        .map(str::as_bytes)
+        .ok_or_else(|| anyhow!("secret is required for HS256"))
+}
+
+fn read_pem(path: Option<&std::path::PathBuf>) -> anyhow::Result<Vec<u8>> {
+    let path = path.ok_or_else(|| anyhow!("key path is required"))?;
+    fs::read(path).with_context(|| format!("failed to read {}", path.display()))
 }
 
 #[async_trait]
 impl<S> FromRequestParts<S> for Claims
 where
     S: Send + Sync,
+    Arc<Keys>: FromRef<S>,
+    Arc<Config>: FromRef<S>,
+    DatabaseConnection: FromRef<S>,
 {
-    type Rejection = String;
+    type Rejection = CustomError;
 
     async fn from_request_parts(
         parts: &mut Parts,
-        _state: &S,
+        state: &S,
     ) -> std::result::Result<Self, Self::Rejection> {
-        // Extract the token from the authorization header
-        let TypedHeader(Authorization(bearer)) = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
-            .await
-            .map_err(|_| "Failed to extract authorization header")?;
+        let db = DatabaseConnection::from_ref(state);
+        // Extract the token from the authorization header, falling back to the session cookie
+        let token = match parts.extract::<TypedHeader<Authorization<Bearer>>>().await {
+            Ok(TypedHeader(Authorization(bearer))) => {
+                if bearer.token().starts_with(ACCESS_TOKEN_PREFIX) {
+                    let scope = parts.extensions.get::<Scope>().copied();
+                    return access_token_claims(&db, bearer.token(), scope).await;
+                }
+                bearer.token().to_string()
+            }
+            Err(_) => session_cookie(parts, &Arc::<Config>::from_ref(state))?,
+        };
         // Decode the user data
-        let token_data = decode::<Claims>(
-            bearer.token(),
-            &Keys::new(b"test").decoding,
-            &Validation::default(),
-        )
-        .map_err(|err| err.to_string())?;
-
-        Ok(token_data.claims)
+        let keys = Arc::<Keys>::from_ref(state);
+        let claims = keys
+            .decode::<Claims>(&token)
+            .map_err(|err| match err.kind() {
+                ErrorKind::ExpiredSignature => CustomError::TokenExpired,
+                ErrorKind::InvalidSignature => CustomError::BadSignature,
+                _ => CustomError::InvalidToken,
+            })?;
+        // Reject tokens whose session was logged out
+        let session = service::query::Query::get_session(&db, claims.sid).await?;
+        if session.is_none_or(|session| session.revoked_at.is_some()) {
+            return Err(CustomError::TokenRevoked);
+        }
+        let user = service::query::Query::get_user(&db, claims.user_id).await?;
+        match user {
+            Some(user) if user.disabled_at.is_none() => Ok(claims),
+            _ => Err(CustomError::Unauthorized("Account is disabled")),
+        }
+    }
+}
+
+/// Access token from the session cookie, after the CSRF check.
+fn session_cookie(parts: &Parts, config: &Config) -> Result<String> {
+    let cookies = parts
+        .extensions
+        .get::<Cookies>()
+        .filter(|_| config.cookie.enabled)
+        .ok_or(CustomError::MissingToken)?;
+    let token = cookies
+        .get(cookie::SESSION_COOKIE)
+        .ok_or(CustomError::MissingToken)?;
+    if !cookie::check_csrf(&parts.method, &parts.headers, cookies) {
+        return Err(CustomError::Csrf);
+    }
+    Ok(token.value().to_string())
+}
+
+async fn access_token_claims(
+    db: &DatabaseConnection,
+    token: &str,
+    scope: Option<Scope>,
+) -> Result<Claims> {
+    let scope = scope.ok_or(CustomError::Unauthorized(
+        "Personal access tokens are not accepted here",
+    ))?;
+    let token = service::query::Query::get_access_token_by_hash(db, &service::token::hash(token))
+        .await?
+        .ok_or(CustomError::InvalidToken)?;
+    let expires_at = token.expires_at.map(|i| i.and_utc().timestamp());
+    if expires_at.is_some_and(|exp| exp <= chrono::Utc::now().timestamp()) {
+        return Err(CustomError::TokenExpired);
+    }
+    let scopes = token.scope_list();
+    if !scopes.iter().any(|i| i == scope.as_str()) {
+        return Err(CustomError::InsufficientScope(scope.as_str()));
+    }
+    let user = service::query::Query::get_user(db, token.user_id)
+        .await?
+        .ok_or(CustomError::InvalidToken)?;
+    if user.disabled_at.is_some() {
+        return Err(CustomError::Unauthorized("Account is disabled"));
+    }
+    service::mutation::Mutation::touch_access_token(db, token.id).await?;
+    Ok(Claims {
+        user_id: user.id,
+        name: user.username,
+        role: user.role,
+        sid: token.id,
+        exp: expires_at.unwrap_or(i64::MAX),
+        scopes: Some(scopes),
+    })
+}
+
+/// Minimum role required by [`RequireRole`].
+pub trait MinRole {
+    const ROLE: RoleEnum;
+}
+
+pub struct Author;
+pub struct Editor;
+pub struct Admin;
+
+impl MinRole for Author {
+    const ROLE: RoleEnum = RoleEnum::Author;
+}
+
+impl MinRole for Editor {
+    const ROLE: RoleEnum = RoleEnum::Editor;
+}
+
+impl MinRole for Admin {
+    const ROLE: RoleEnum = RoleEnum::Admin;
+}
+
+/// Claims of a caller holding at least the role `R`, e.g. `RequireRole<Editor>`.
+pub struct RequireRole<R>(pub Claims, pub PhantomData<R>);
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    R: MinRole,
+    Arc<Keys>: FromRef<S>,
+    Arc<Config>: FromRef<S>,
+    DatabaseConnection: FromRef<S>,
+{
+    type Rejection = CustomError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        if !claims.has_role(R::ROLE) {
+            return Err(CustomError::Forbidden);
+        }
+        Ok(RequireRole(claims, PhantomData))
     }
 }
@@ -0,0 +1,281 @@
+use std::{env, fs, path::PathBuf, str::FromStr};
+
+use anyhow::Context;
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use service::password::PasswordConfig;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub password: PasswordConfig,
+    pub jwt: JwtConfig,
+    /// User promoted to admin at startup, so a fresh install gets its first admin.
+    pub admin_username: Option<String>,
+    /// Access token lifetime in seconds.
+    pub access_token_ttl: i64,
+    /// Refresh token lifetime in seconds, extended on every refresh.
+    pub refresh_token_ttl: i64,
+    /// Email verification link lifetime in seconds.
+    pub verify_email_ttl: i64,
+    /// Password reset token lifetime in seconds.
+    pub reset_password_ttl: i64,
+    /// Lifetime in seconds of the token bridging the password and TOTP steps.
+    pub mfa_token_ttl: i64,
+    /// Issuer shown in authenticator apps.
+    pub totp_issuer: String,
+    /// Base URL of the API used to build links in mails.
+    pub public_url: String,
+    pub mail: MailConfig,
+    /// OpenID Connect login, disabled unless `OIDC_ISSUER` is set.
+    pub oidc: Option<OidcConfig>,
+    pub cookie: CookieConfig,
+    pub login_throttle: LoginThrottleConfig,
+    /// Take the client address from `X-Forwarded-For` when running behind a
+    /// reverse proxy.
+    pub trust_proxy_headers: bool,
+    /// Days a trashed blog is kept before it is purged for good.
+    pub trash_retention_days: i64,
+    /// Seconds between runs of the trash purge job.
+    pub trash_purge_interval: u64,
+    /// Seconds between checks for blogs due to be published or unpublished.
+    pub publish_interval: u64,
+}
+
+/// Session cookies for browser clients, in addition to bearer tokens.
+#[derive(Debug, Clone)]
+pub struct CookieConfig {
+    pub enabled: bool,
+    /// Only send cookies over HTTPS; disable for plain HTTP development setups.
+    pub secure: bool,
+    pub domain: Option<String>,
+}
+
+impl CookieConfig {
+    fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            enabled: env_or("COOKIE_AUTH", false)?,
+            secure: env_or("COOKIE_SECURE", true)?,
+            domain: env::var("COOKIE_DOMAIN").ok(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL; the provider is configured from its discovery document.
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Callback URL registered at the provider.
+    pub redirect_url: String,
+    pub scopes: String,
+}
+
+impl OidcConfig {
+    fn from_env(public_url: &str) -> anyhow::Result<Option<Self>> {
+        let Ok(issuer) = env::var("OIDC_ISSUER") else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            issuer,
+            client_id: env::var("OIDC_CLIENT_ID").context("OIDC_CLIENT_ID must be set")?,
+            client_secret: env::var("OIDC_CLIENT_SECRET").ok(),
+            redirect_url: env_or(
+                "OIDC_REDIRECT_URL",
+                format!("{public_url}/user/oidc/callback"),
+            )?,
+            scopes: env_or("OIDC_SCOPES", "openid email profile".to_string())?,
+        }))
+    }
+}
+
+/// Failed login limits, applied per username and per client address.
+#[derive(Debug, Clone)]
+pub struct LoginThrottleConfig {
+    /// Failures on one account before it is locked.
+    pub lockout_threshold: i32,
+    /// Lockout duration in seconds, also the window after which failures are forgotten.
+    pub lockout_duration: i64,
+    /// Delay in seconds after the first failure, doubled on every further failure.
+    pub backoff_base: i64,
+    /// Upper bound of the delay in seconds.
+    pub backoff_max: i64,
+}
+
+impl LoginThrottleConfig {
+    fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            lockout_threshold: env_or("LOGIN_LOCKOUT_THRESHOLD", 10)?,
+            lockout_duration: env_or("LOGIN_LOCKOUT_DURATION", 15 * 60)?,
+            backoff_base: env_or("LOGIN_BACKOFF_BASE", 1)?,
+            backoff_max: env_or("LOGIN_BACKOFF_MAX", 5 * 60)?,
+        })
+    }
+
+    /// 第 `failures` 次失败后需要等待的秒数
+    pub fn backoff(&self, failures: i32) -> i64 {
+        let exp = (failures - 1).clamp(0, 30) as u32;
+        self.backoff_base
+            .saturating_mul(1 << exp)
+            .min(self.backoff_max)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    pub from: String,
+    pub transport: MailTransportConfig,
+}
+
+#[derive(Debug, Clone)]
+pub enum MailTransportConfig {
+    Smtp { url: String },
+    File { dir: PathBuf },
+}
+
+impl MailConfig {
+    fn from_env() -> anyhow::Result<Self> {
+        let transport = match env::var("MAIL_TRANSPORT").as_deref() {
+            Ok("smtp") => MailTransportConfig::Smtp {
+                url: env::var("SMTP_URL").context("SMTP_URL must be set")?,
+            },
+            Ok("file") | Err(_) => MailTransportConfig::File {
+                dir: env_or("MAIL_OUTBOX_DIR", PathBuf::from("./outbox"))?,
+            },
+            Ok(other) => anyhow::bail!("unknown MAIL_TRANSPORT {other}"),
+        };
+        Ok(Self {
+            from: env_or("MAIL_FROM", "Blog <noreply@localhost>".to_string())?,
+            transport,
+        })
+    }
+}
+
+/// JWT key set, read from the JSON file named by `JWT_KEYS_FILE`.
+///
+/// Tokens are signed with the `active` key; every listed key is accepted for
+/// verification, so retired keys can stay in the list until their tokens expire.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtConfig {
+    pub active: String,
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub alg: Algorithm,
+    /// Shared secret for HS256.
+    pub secret: Option<String>,
+    /// PKCS#8 PEM private key for RS256/EdDSA, only needed for the active key.
+    pub private_key: Option<PathBuf>,
+    /// SPKI PEM public key for RS256/EdDSA.
+    pub public_key: Option<PathBuf>,
+}
+
+impl JwtConfig {
+    fn from_env() -> anyhow::Result<Self> {
+        if let Ok(path) = env::var("JWT_KEYS_FILE") {
+            let content =
+                fs::read_to_string(&path).with_context(|| format!("failed to read {path}"))?;
+            return serde_json::from_str(&content).with_context(|| format!("invalid {path}"));
+        }
+        // 未配置密钥文件时退回到单个 HS256 密钥
+        let secret = env::var("JWT_SECRET").context("JWT_KEYS_FILE or JWT_SECRET must be set")?;
+        Ok(Self {
+            active: "default".to_string(),
+            keys: vec![JwtKeyConfig {
+                kid: "default".to_string(),
+                alg: Algorithm::HS256,
+                secret: Some(secret),
+                private_key: None,
+                public_key: None,
+            }],
+        })
+    }
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default_password = PasswordConfig::default();
+        let password = PasswordConfig {
+            m_cost: env_or("ARGON2_M_COST", default_password.m_cost)?,
+            t_cost: env_or("ARGON2_T_COST", default_password.t_cost)?,
+            p_cost: env_or("ARGON2_P_COST", default_password.p_cost)?,
+        };
+        // 提前校验参数，避免在首次登录时才发现配置错误
+        password.hash("")?;
+        let public_url = env_or("PUBLIC_URL", "http://localhost:8000".to_string())?;
+        Ok(Self {
+            password,
+            jwt: JwtConfig::from_env()?,
+            admin_username: env::var("ADMIN_USERNAME").ok().filter(|i| !i.is_empty()),
+            access_token_ttl: env_or("ACCESS_TOKEN_TTL", 15 * 60)?,
+            refresh_token_ttl: env_or("REFRESH_TOKEN_TTL", 30 * 24 * 60 * 60)?,
+            verify_email_ttl: env_or("VERIFY_EMAIL_TTL", 24 * 60 * 60)?,
+            reset_password_ttl: env_or("RESET_PASSWORD_TTL", 60 * 60)?,
+            mfa_token_ttl: env_or("MFA_TOKEN_TTL", 5 * 60)?,
+            totp_issuer: env_or("TOTP_ISSUER", "Blog".to_string())?,
+            mail: MailConfig::from_env()?,
+            oidc: OidcConfig::from_env(&public_url)?,
+            cookie: CookieConfig::from_env()?,
+            public_url,
+            login_throttle: LoginThrottleConfig::from_env()?,
+            trust_proxy_headers: env_or("TRUST_PROXY_HEADERS", false)?,
+            trash_retention_days: env_or("TRASH_RETENTION_DAYS", 30)?,
+            trash_purge_interval: env_or("TRASH_PURGE_INTERVAL", 60 * 60)?,
+            publish_interval: env_or("PUBLISH_INTERVAL", 60)?,
+        })
+    }
+}
+
+fn env_or<T>(key: &str, default: T) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match env::var(key) {
+        Ok(value) => value.parse().with_context(|| format!("invalid {key}")),
+        Err(_) => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle() -> LoginThrottleConfig {
+        LoginThrottleConfig {
+            lockout_threshold: 10,
+            lockout_duration: 15 * 60,
+            backoff_base: 1,
+            backoff_max: 5 * 60,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_per_failure() {
+        let config = throttle();
+        let delays: Vec<i64> = (1..=6).map(|n| config.backoff(n)).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32]);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let config = throttle();
+        assert_eq!(config.backoff(9), 256);
+        assert_eq!(config.backoff(10), config.backoff_max);
+        assert_eq!(config.backoff(i32::MAX), config.backoff_max);
+    }
+
+    #[test]
+    fn backoff_before_any_failure_is_the_base() {
+        let config = LoginThrottleConfig {
+            backoff_base: 3,
+            ..throttle()
+        };
+        assert_eq!(config.backoff(0), 3);
+        assert_eq!(config.backoff(-1), 3);
+        assert_eq!(config.backoff(2), 6);
+    }
+}
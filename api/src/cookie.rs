@@ -0,0 +1,209 @@
+//! Optional cookie sessions for browser clients.
+//!
+//! Login stores the access token in an HttpOnly cookie. Requests authenticated
+//! by that cookie must echo the readable CSRF cookie in the `X-CSRF-Token`
+//! header (double submit) unless their method is safe.
+
+use axum::http::{HeaderMap, Method};
+use subtle::ConstantTimeEq;
+use tower_cookies::{
+    cookie::{time::Duration, SameSite},
+    Cookie, Cookies,
+};
+
+use crate::{
+    auth::TokenPair,
+    config::{Config, CookieConfig},
+};
+
+pub const SESSION_COOKIE: &str = "blog_session";
+pub const REFRESH_COOKIE: &str = "blog_refresh";
+pub const CSRF_COOKIE: &str = "blog_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+pub const OIDC_STATE_COOKIE: &str = "blog_oidc_state";
+/// 刷新令牌只发送给刷新接口
+const REFRESH_PATH: &str = "/user/refresh";
+/// OIDC 的 state 只发送给登录和回调接口
+const OIDC_PATH: &str = "/user/oidc";
+
+fn build(
+    config: &CookieConfig,
+    name: &'static str,
+    value: String,
+    path: &'static str,
+    same_site: SameSite,
+) -> Cookie<'static> {
+    let mut cookie = Cookie::build((name, value))
+        .path(path)
+        .same_site(same_site)
+        .secure(config.secure)
+        .http_only(name != CSRF_COOKIE)
+        .build();
+    if let Some(domain) = &config.domain {
+        cookie.set_domain(domain.clone());
+    }
+    cookie
+}
+
+/// Stores a freshly issued token pair in cookies when cookie auth is enabled.
+pub fn set_session(cookies: &Cookies, config: &Config, tokens: &TokenPair) {
+    let cookie_config = &config.cookie;
+    if !cookie_config.enabled {
+        return;
+    }
+    let mut session = build(
+        cookie_config,
+        SESSION_COOKIE,
+        tokens.access_token.clone(),
+        "/",
+        SameSite::Lax,
+    );
+    session.set_max_age(Duration::seconds(tokens.expires_in));
+    cookies.add(session);
+    let mut refresh = build(
+        cookie_config,
+        REFRESH_COOKIE,
+        tokens.refresh_token.clone(),
+        REFRESH_PATH,
+        SameSite::Strict,
+    );
+    refresh.set_max_age(Duration::seconds(config.refresh_token_ttl));
+    cookies.add(refresh);
+    // 刷新时保留已有的 CSRF 令牌，避免已打开的页面失效
+    if cookies.get(CSRF_COOKIE).is_none() {
+        let mut csrf = build(
+            cookie_config,
+            CSRF_COOKIE,
+            service::token::generate(),
+            "/",
+            SameSite::Lax,
+        );
+        csrf.set_max_age(Duration::seconds(config.refresh_token_ttl));
+        cookies.add(csrf);
+    }
+}
+
+pub fn clear_session(cookies: &Cookies, config: &CookieConfig) {
+    if !config.enabled {
+        return;
+    }
+    for (name, path) in [
+        (SESSION_COOKIE, "/"),
+        (REFRESH_COOKIE, REFRESH_PATH),
+        (CSRF_COOKIE, "/"),
+    ] {
+        cookies.remove(build(config, name, String::new(), path, SameSite::Lax));
+    }
+}
+
+/// Binds an OIDC login to the browser that started it. Always set, since the
+/// provider redirects the browser back even when cookie auth is disabled.
+pub fn set_oidc_state(cookies: &Cookies, config: &CookieConfig, state: &str, ttl: i64) {
+    // 回调是从身份提供方跳转回来的跨站导航，Strict 不会携带该 Cookie
+    let mut cookie = build(
+        config,
+        OIDC_STATE_COOKIE,
+        state.to_string(),
+        OIDC_PATH,
+        SameSite::Lax,
+    );
+    cookie.set_max_age(Duration::seconds(ttl));
+    cookies.add(cookie);
+}
+
+/// Removes the OIDC state cookie and reports whether it matched `state`.
+pub fn take_oidc_state(cookies: &Cookies, config: &CookieConfig, state: &str) -> bool {
+    let Some(expected) = cookies.get(OIDC_STATE_COOKIE) else {
+        return false;
+    };
+    let matches = expected.value().as_bytes().ct_eq(state.as_bytes()).into();
+    cookies.remove(build(
+        config,
+        OIDC_STATE_COOKIE,
+        String::new(),
+        OIDC_PATH,
+        SameSite::Lax,
+    ));
+    matches
+}
+
+/// Double submit check for a request authenticated by cookie.
+pub fn check_csrf(method: &Method, headers: &HeaderMap, cookies: &Cookies) -> bool {
+    if method.is_safe() {
+        return true;
+    }
+    let Some(expected) = cookies.get(CSRF_COOKIE) else {
+        return false;
+    };
+    let Some(actual) = headers.get(CSRF_HEADER) else {
+        return false;
+    };
+    expected.value().as_bytes().ct_eq(actual.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookies(token: Option<&str>) -> Cookies {
+        let cookies = Cookies::default();
+        if let Some(token) = token {
+            cookies.add(Cookie::new(CSRF_COOKIE, token.to_owned()));
+        }
+        cookies
+    }
+
+    fn headers(token: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            headers.insert(CSRF_HEADER, token.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn safe_methods_skip_the_check() {
+        assert!(check_csrf(&Method::GET, &headers(None), &cookies(None)));
+        assert!(check_csrf(&Method::HEAD, &headers(None), &cookies(None)));
+        assert!(check_csrf(&Method::OPTIONS, &headers(None), &cookies(None)));
+    }
+
+    #[test]
+    fn unsafe_methods_need_both_cookie_and_header() {
+        assert!(!check_csrf(&Method::POST, &headers(None), &cookies(None)));
+        assert!(!check_csrf(
+            &Method::POST,
+            &headers(Some("abc")),
+            &cookies(None)
+        ));
+        assert!(!check_csrf(
+            &Method::POST,
+            &headers(None),
+            &cookies(Some("abc"))
+        ));
+    }
+
+    #[test]
+    fn header_must_match_cookie() {
+        assert!(check_csrf(
+            &Method::POST,
+            &headers(Some("abc")),
+            &cookies(Some("abc"))
+        ));
+        assert!(check_csrf(
+            &Method::DELETE,
+            &headers(Some("abc")),
+            &cookies(Some("abc"))
+        ));
+        assert!(!check_csrf(
+            &Method::POST,
+            &headers(Some("abd")),
+            &cookies(Some("abc"))
+        ));
+        assert!(!check_csrf(
+            &Method::PUT,
+            &headers(Some("ab")),
+            &cookies(Some("abc"))
+        ));
+    }
+}
@@ -1,4 +1,7 @@
-use axum::response::{IntoResponse, Response};
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
 use migration::DbErr;
 use thiserror::Error;
 use tokio::io;
@@ -11,6 +14,34 @@ pub enum CustomError {
     Database(#[from] DbErr),
     #[error("Unauthorized")]
     Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("{0}")]
+    Unauthorized(&'static str),
+    #[error("Authorization token is missing")]
+    MissingToken,
+    #[error("Authorization token has expired")]
+    TokenExpired,
+    #[error("Authorization token is invalid")]
+    InvalidToken,
+    #[error("Authorization token signature is invalid")]
+    BadSignature,
+    #[error("Session has been revoked")]
+    TokenRevoked,
+    #[error("Token lacks the {0} scope")]
+    InsufficientScope(&'static str),
+    #[error("CSRF token missing or invalid")]
+    Csrf,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("{0}")]
+    NotFound(&'static str),
+    #[error("{0}")]
+    Conflict(&'static str),
+    #[error("If-Match header is required")]
+    PreconditionRequired,
+    #[error("Account locked until {0}")]
+    AccountLocked(chrono::NaiveDateTime),
+    #[error("Too many failed login attempts, retry in {0} seconds")]
+    TooManyAttempts(i64),
     #[error(transparent)]
     Validation(#[from] validator::ValidationErrors),
     #[error(transparent)]
@@ -24,17 +55,89 @@ pub enum CustomError {
 impl CustomError {
     pub fn code(&self) -> i32 {
         match self {
-            &CustomError::Jwt(_) => 9527,
-            &CustomError::Database(_) => 9528,
+            CustomError::Jwt(_) | CustomError::Unauthorized(_) => 9527,
+            CustomError::Database(_) => 9528,
+            CustomError::Forbidden => 9529,
+            CustomError::AccountLocked(_) => 9530,
+            CustomError::TooManyAttempts(_) => 9531,
+            CustomError::MissingToken => 9532,
+            CustomError::TokenExpired => 9533,
+            CustomError::InvalidToken => 9534,
+            CustomError::TokenRevoked => 9535,
+            CustomError::InsufficientScope(_) => 9536,
+            CustomError::Csrf => 9537,
+            CustomError::BadSignature => 9538,
+            CustomError::NotFound(_) => 9539,
+            CustomError::Conflict(_) => 9540,
+            CustomError::PreconditionRequired => 9541,
             _ => -1,
         }
     }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            CustomError::Jwt(_)
+            | CustomError::Unauthorized(_)
+            | CustomError::MissingToken
+            | CustomError::TokenExpired
+            | CustomError::InvalidToken
+            | CustomError::BadSignature
+            | CustomError::TokenRevoked => StatusCode::UNAUTHORIZED,
+            CustomError::Forbidden | CustomError::InsufficientScope(_) | CustomError::Csrf => {
+                StatusCode::FORBIDDEN
+            }
+            CustomError::NotFound(_) => StatusCode::NOT_FOUND,
+            CustomError::Conflict(_) => StatusCode::CONFLICT,
+            CustomError::PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+            CustomError::AccountLocked(_) => StatusCode::LOCKED,
+            CustomError::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::OK,
+        }
+    }
+
+    /// `WWW-Authenticate` challenge for bearer token failures, see RFC 6750.
+    fn challenge(&self) -> Option<String> {
+        let error = match self {
+            CustomError::MissingToken => return Some(format!("Bearer realm=\"{REALM}\"")),
+            CustomError::InsufficientScope(scope) => {
+                return Some(format!(
+                    "Bearer realm=\"{REALM}\", error=\"insufficient_scope\", scope=\"{scope}\""
+                ))
+            }
+            CustomError::Jwt(_)
+            | CustomError::Unauthorized(_)
+            | CustomError::TokenExpired
+            | CustomError::InvalidToken
+            | CustomError::BadSignature
+            | CustomError::TokenRevoked => "invalid_token",
+            _ => return None,
+        };
+        Some(format!(
+            "Bearer realm=\"{REALM}\", error=\"{error}\", error_description=\"{self}\""
+        ))
+    }
 }
 
+const REALM: &str = "blog";
+
 impl IntoResponse for CustomError {
     fn into_response(self) -> Response {
-        CustomResponse::<()>::error_with_code(self.code(), &self)
-            .to_json()
-            .into_response()
+        let status = self.status();
+        let body = CustomResponse::<()>::error_with_code(self.code(), &self).into_json();
+        let mut response = (status, body).into_response();
+        if let Some(challenge) = self
+            .challenge()
+            .and_then(|i| HeaderValue::from_str(&i).ok())
+        {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        if let CustomError::TooManyAttempts(seconds) = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 }
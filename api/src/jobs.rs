@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use entity::blog;
+use service::sea_orm::{ColumnTrait, DatabaseConnection};
+
+use crate::state::AppState;
+
+/// Starts the periodic background jobs.
+pub fn spawn(state: &AppState) {
+    let db = state.db.clone();
+    let retention = chrono::Duration::days(state.config.trash_retention_days);
+    let interval = Duration::from_secs(state.config.trash_purge_interval.max(1));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            purge_trash(&db, retention).await;
+        }
+    });
+    let db = state.db.clone();
+    let interval = Duration::from_secs(state.config.publish_interval.max(1));
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            publish_scheduled(&db).await;
+        }
+    });
+}
+
+/// 发布到期的定时文章并下线过期文章。每篇文章只会被其中一个实例更新，
+/// 公开列表不依赖这里的执行进度
+async fn publish_scheduled(db: &DatabaseConnection) {
+    let now = chrono::Utc::now().naive_utc();
+    match service::mutation::Mutation::publish_due_blogs(db, now).await {
+        Ok(blogs) if blogs.is_empty() => {}
+        Ok(blogs) => tracing::info!(count = blogs.len(), "published scheduled blogs"),
+        Err(e) => tracing::error!(error = %e, "failed to publish scheduled blogs"),
+    }
+    match service::mutation::Mutation::unpublish_expired_blogs(db, now).await {
+        Ok(blogs) if blogs.is_empty() => {}
+        Ok(blogs) => tracing::info!(count = blogs.len(), "unpublished expired blogs"),
+        Err(e) => tracing::error!(error = %e, "failed to unpublish expired blogs"),
+    }
+}
+
+/// 彻底删除在回收站中超过保留期的文章
+async fn purge_trash(db: &DatabaseConnection, retention: chrono::Duration) {
+    let cutoff = chrono::Utc::now().naive_utc() - retention;
+    match service::mutation::Mutation::purge_blogs(db, blog::Column::DeletedAt.lt(cutoff)).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!(count = n, "purged trashed blogs"),
+        Err(e) => tracing::error!(error = %e, "failed to purge trashed blogs"),
+    }
+}
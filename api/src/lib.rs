@@ -1,24 +1,59 @@
+mod audit;
 mod auth;
+mod client;
+mod config;
+mod cookie;
 mod error;
+mod jobs;
+mod mail;
+mod oidc;
 mod openapi;
 mod response;
+mod state;
+mod throttle;
 mod util;
 mod v1;
 mod validate;
 
-use axum::{http::StatusCode, response::IntoResponse, Router};
+use auth::Keys;
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    middleware::map_response,
+    response::{IntoResponse, Response},
+    Router,
+};
+use config::Config;
 use dotenvy::dotenv;
 use migration::{extension::postgres::Extension, ConnectionTrait, PostgresQueryBuilder};
 use openapi::ApiDoc;
 use service::sea_orm::{ConnectOptions, Database, DatabaseBackend, Statement};
-use std::{env, time::Duration};
-use tower_http::cors::{Any, CorsLayer};
+use state::AppState;
+use std::{env, net::SocketAddr, sync::Arc, time::Duration};
+use tower::ServiceBuilder;
+use tower_cookies::CookieManagerLayer;
+use tower_http::{
+    cors::{Any, CorsLayer},
+    services::ServeDir,
+};
+use tracing_subscriber::EnvFilter;
 use utoipa::OpenApi;
 use utoipa_scalar::{Scalar, Servable};
 
 #[tokio::main]
 pub async fn main() -> anyhow::Result<()> {
     dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+    let config = Config::from_env()?;
+    let keys = Keys::from_config(&config.jwt)?;
+    let mailer = mail::from_config(&config.mail)?;
+    let oidc = config
+        .oidc
+        .clone()
+        .map(|oidc| Arc::new(oidc::OidcClient::new(oidc)));
     let db_url = env::var("DATABASE_URL").unwrap();
     let mut opt = ConnectOptions::new(db_url);
     opt.max_connections(100)
@@ -37,24 +72,67 @@ pub async fn main() -> anyhow::Result<()> {
         .to_string(PostgresQueryBuilder);
     db.execute(Statement::from_string(DatabaseBackend::Postgres, stmt))
         .await?;
+    if let Some(username) = &config.admin_username {
+        match service::mutation::Mutation::promote_admin(&db, username).await? {
+            Some(user) => tracing::info!(user_id = %user.id, username, "promoted to admin"),
+            None => tracing::warn!(username, "ADMIN_USERNAME does not match any user"),
+        }
+    }
+    let state = AppState {
+        db,
+        config: Arc::new(config),
+        keys: Arc::new(keys),
+        mailer,
+        oidc,
+    };
+    jobs::spawn(&state);
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
     let app = Router::new()
         .merge(Scalar::with_url("/", ApiDoc::openapi()))
-        .nest("/api/v1", v1::article::route())
-        .merge(v1::user::route())
+        .nest("/api/v1", v1::article::route(state.clone()))
+        .merge(v1::user::route(state.clone()))
+        .merge(v1::oidc::route())
+        .merge(v1::audit::route(state.clone()))
         .merge(v1::upload::route())
+        .nest_service(
+            "/uploads",
+            ServiceBuilder::new()
+                .layer(map_response(harden_upload))
+                .service(ServeDir::new("./uploads")),
+        )
+        .layer(CookieManagerLayer::new())
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_headers(Any)
-                .allow_methods(Any),
+                .allow_methods(Any)
+                .expose_headers([header::ETAG]),
         )
-        .with_state(db)
+        .with_state(state)
         .fallback(handle_rejection);
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
 
+/// Uploaded files are user content; never let the browser run them as pages
+/// on this origin.
+async fn harden_upload<B>(mut response: Response<B>) -> Response<B> {
+    let headers = response.headers_mut();
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static("sandbox"),
+    );
+    response
+}
+
 async fn handle_rejection() -> impl IntoResponse {
     (StatusCode::NOT_FOUND, "404 Not Found")
 }
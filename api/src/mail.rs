@@ -0,0 +1,80 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use axum::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use service::sea_orm::prelude::Uuid;
+use tokio::fs;
+
+use crate::config::{MailConfig, MailTransportConfig};
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, mail: Mail) -> anyhow::Result<()>;
+}
+
+pub fn from_config(config: &MailConfig) -> anyhow::Result<Arc<dyn MailTransport>> {
+    let from: Mailbox = config.from.parse().context("invalid MAIL_FROM")?;
+    Ok(match &config.transport {
+        MailTransportConfig::Smtp { url } => Arc::new(SmtpTransport {
+            mailer: AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+                .context("invalid SMTP_URL")?
+                .build(),
+            from,
+        }),
+        MailTransportConfig::File { dir } => Arc::new(FileOutbox {
+            dir: dir.clone(),
+            from,
+        }),
+    })
+}
+
+fn build_message(from: &Mailbox, mail: Mail) -> anyhow::Result<Message> {
+    Ok(Message::builder()
+        .from(from.clone())
+        .to(mail.to.parse()?)
+        .subject(mail.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(mail.body)?)
+}
+
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+#[async_trait]
+impl MailTransport for SmtpTransport {
+    async fn send(&self, mail: Mail) -> anyhow::Result<()> {
+        self.mailer.send(build_message(&self.from, mail)?).await?;
+        Ok(())
+    }
+}
+
+/// Writes every mail as an `.eml` file instead of delivering it, for local
+/// development and testing.
+pub struct FileOutbox {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+#[async_trait]
+impl MailTransport for FileOutbox {
+    async fn send(&self, mail: Mail) -> anyhow::Result<()> {
+        let message = build_message(&self.from, mail)?;
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(format!("{}.eml", Uuid::new_v4()));
+        fs::write(path, message.formatted()).await?;
+        Ok(())
+    }
+}
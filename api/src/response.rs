@@ -1,5 +1,6 @@
 use axum::Json;
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::error::CustomError;
@@ -33,7 +34,115 @@ impl<T: Serialize> CustomResponse<T> {
     pub fn error_with_code(code: i32, e: &CustomError) -> Self {
         Self::failed(code, &e.to_string())
     }
-    pub fn to_json(self) -> JsonResponse<T> {
+    pub fn into_json(self) -> JsonResponse<T> {
         Json(self)
     }
 }
+
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+pub const MAX_PAGE_SIZE: u64 = 100;
+/// Keeps `(page - 1) * page_size` far below the range of an SQL `OFFSET`.
+pub const MAX_PAGE: u64 = 1_000_000;
+
+/// Normalizes 1-based page parameters from a query string.
+pub fn page_params(page: Option<u64>, page_size: Option<u64>) -> (u64, u64) {
+    (
+        page.unwrap_or(1).clamp(1, MAX_PAGE),
+        page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE),
+    )
+}
+
+/// One page of a list together with the total number of items.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T: Serialize> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: u64,
+    pub page_size: u64,
+}
+
+/// Page of a list that also supports keyset pagination. `page` is only set
+/// when the page was requested by number rather than by cursor.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorPage<T: Serialize> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: Option<u64>,
+    pub page_size: u64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+/// Opaque cursor string handed to clients.
+pub fn encode_cursor<T: Serialize>(cursor: &T) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+pub fn decode_cursor<T: DeserializeOwned>(cursor: &str) -> Result<T> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| anyhow::anyhow!("无效的分页游标").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::blog::{Cursor, CursorKey, SortField, SortOrder};
+    use service::sea_orm::prelude::{DateTime, Uuid};
+
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        let time: DateTime = "2024-05-01T12:30:00.123456".parse().unwrap();
+        let id = Uuid::from_u128(42);
+        let encoded = encode_cursor(&Cursor {
+            sort: SortField::UpdateTime,
+            order: SortOrder::Asc,
+            key: CursorKey::Time(time),
+            id,
+            before: true,
+        });
+        assert!(!encoded.contains(['+', '/', '=']));
+
+        let cursor: Cursor = decode_cursor(&encoded).ok().unwrap();
+        assert_eq!(cursor.sort, SortField::UpdateTime);
+        assert_eq!(cursor.order, SortOrder::Asc);
+        assert!(matches!(cursor.key, CursorKey::Time(t) if t == time));
+        assert_eq!(cursor.id, id);
+        assert!(cursor.before);
+
+        let encoded = encode_cursor(&Cursor {
+            sort: SortField::Title,
+            order: SortOrder::Desc,
+            key: CursorKey::Title("你好, world".into()),
+            id,
+            before: false,
+        });
+        let cursor: Cursor = decode_cursor(&encoded).ok().unwrap();
+        assert!(matches!(cursor.key, CursorKey::Title(t) if t == "你好, world"));
+        assert!(!cursor.before);
+    }
+
+    #[test]
+    fn invalid_cursors_are_rejected() {
+        assert!(decode_cursor::<Cursor>("").is_err());
+        assert!(decode_cursor::<Cursor>("not a cursor!").is_err());
+        assert!(decode_cursor::<Cursor>(&URL_SAFE_NO_PAD.encode("{}")).is_err());
+    }
+
+    #[test]
+    fn page_params_are_clamped() {
+        assert_eq!(page_params(None, None), (1, DEFAULT_PAGE_SIZE));
+        assert_eq!(page_params(Some(0), Some(0)), (1, 1));
+        assert_eq!(
+            page_params(Some(u64::MAX), Some(u64::MAX)),
+            (MAX_PAGE, MAX_PAGE_SIZE)
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use service::sea_orm::DatabaseConnection;
+
+use crate::{auth::Keys, config::Config, mail::MailTransport, oidc::OidcClient};
+
+#[derive(Clone, FromRef)]
+pub struct AppState {
+    pub db: DatabaseConnection,
+    pub config: Arc<Config>,
+    pub keys: Arc<Keys>,
+    pub mailer: Arc<dyn MailTransport>,
+    pub oidc: Option<Arc<OidcClient>>,
+}
@@ -0,0 +1,81 @@
+//! 登录失败限制：按用户名与客户端地址分别计数，失败后指数退避，
+//! 同一账号失败次数达到阈值后临时锁定。
+
+use std::net::IpAddr;
+
+use chrono::{Duration, Utc};
+use service::{mutation::Mutation, query::Query, sea_orm::DatabaseConnection};
+
+use crate::{config::LoginThrottleConfig, error::CustomError, response::Result};
+
+pub fn user_key(username: &str) -> String {
+    format!("user:{username}")
+}
+
+pub fn ip_key(ip: IpAddr) -> String {
+    format!("ip:{ip}")
+}
+
+/// 一次尝试占用账号与地址的最长时间，处理中途出错时到期自动释放
+const ATTEMPT_HOLD: i64 = 10;
+
+/// 原子地占用本次登录尝试：账号或地址处于退避、锁定或已有尝试进行中时拒绝，
+/// 避免并发请求在记录失败之前同时通过校验。凭据正确后调用 [`release`]，
+/// 错误时调用 [`record_failure`]
+pub async fn check(
+    db: &DatabaseConnection,
+    config: &LoginThrottleConfig,
+    username: &str,
+    ip: IpAddr,
+) -> Result<()> {
+    let now = Utc::now().naive_utc();
+    let user_key = user_key(username);
+    let keys = vec![user_key.clone(), ip_key(ip)];
+    let until = now + Duration::seconds(ATTEMPT_HOLD);
+    if Mutation::reserve_login(db, keys.clone(), until).await? {
+        return Ok(());
+    }
+    let entries = Query::get_login_throttles(db, keys).await?;
+    let mut wait = 1;
+    for entry in entries {
+        let Some(until) = entry.locked_until.filter(|until| *until > now) else {
+            continue;
+        };
+        if entry.key == user_key && entry.failures >= config.lockout_threshold {
+            return Err(CustomError::AccountLocked(until));
+        }
+        wait = wait.max((until - now).num_seconds() + 1);
+    }
+    Err(CustomError::TooManyAttempts(wait))
+}
+
+/// 凭据校验通过后释放 [`check`] 的占用，失败计数保持不变
+pub async fn release(db: &DatabaseConnection, username: &str, ip: IpAddr) -> Result<()> {
+    Ok(Mutation::release_login(db, vec![user_key(username), ip_key(ip)]).await?)
+}
+
+/// 记录一次失败登录，并设置下一次允许尝试的时间
+pub async fn record_failure(
+    db: &DatabaseConnection,
+    config: &LoginThrottleConfig,
+    username: &str,
+    ip: IpAddr,
+) -> Result<()> {
+    let now = Utc::now().naive_utc();
+    let window_start = now - Duration::seconds(config.lockout_duration);
+    for (key, lockable) in [(user_key(username), true), (ip_key(ip), false)] {
+        let entry = Mutation::record_login_failure(db, &key, window_start).await?;
+        let seconds = if lockable && entry.failures >= config.lockout_threshold {
+            config.lockout_duration
+        } else {
+            config.backoff(entry.failures)
+        };
+        Mutation::lock_login(db, &key, now + Duration::seconds(seconds)).await?;
+    }
+    Ok(())
+}
+
+/// 登录成功后清除账号的失败记录；地址计数保留，避免攻击者用自己的账号重置
+pub async fn reset(db: &DatabaseConnection, username: &str) -> Result<u64> {
+    Ok(Mutation::clear_login_failures(db, &user_key(username)).await?)
+}
@@ -8,6 +8,18 @@ use tokio_util::io::StreamReader;
 
 use crate::error::CustomError;
 
+/// Extension of a PNG, JPEG, GIF or WebP image recognised by its leading
+/// bytes, regardless of the name or content type the client sent.
+pub fn image_extension(header: &[u8]) -> Option<&'static str> {
+    match header {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("jpg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("webp"),
+        _ => None,
+    }
+}
+
 pub fn path_is_valid(path: &str) -> bool {
     let path = std::path::Path::new(path);
     let mut components = path.components().peekable();
@@ -30,7 +42,7 @@ where
 
     Ok(async {
         // Convert the stream into an `AsyncRead`.
-        let body_with_io_error = stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err));
+        let body_with_io_error = stream.map_err(io::Error::other);
         let body_reader = StreamReader::new(body_with_io_error);
         futures::pin_mut!(body_reader);
 
@@ -49,3 +61,27 @@ where
     }
     .await?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_extension_reads_magic_bytes() {
+        assert_eq!(
+            image_extension(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("png")
+        );
+        assert_eq!(image_extension(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("jpg"));
+        assert_eq!(image_extension(b"GIF89a\x01\0\x01\0"), Some("gif"));
+        assert_eq!(image_extension(b"RIFF\x24\0\0\0WEBPVP8 "), Some("webp"));
+    }
+
+    #[test]
+    fn image_extension_rejects_other_content() {
+        assert_eq!(image_extension(b"<html><script>"), None);
+        assert_eq!(image_extension(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(image_extension(b"\x89PN"), None);
+        assert_eq!(image_extension(b""), None);
+    }
+}
@@ -1,89 +1,823 @@
-use crate::auth::Claims;
-use crate::response::{CustomResponse, Result};
-use axum::middleware::from_extractor;
-use axum::routing::{get, post};
-use axum::Router;
-use axum::{extract::State, Form, Json};
-use entity::{blog, blog_tag, tag};
-use entity::{blog::CombineBlog, category};
-use service::sea_orm::{prelude::Uuid, DatabaseConnection, TryIntoModel};
+use crate::audit::{self, Actor, Audit};
+use crate::auth::{Admin, Author, Claims, Editor, RequireRole, Scope};
+use crate::error::CustomError;
+use crate::response::{
+    decode_cursor, encode_cursor, page_params, CursorPage, CustomResponse, Page, Result,
+};
+use crate::state::AppState;
+use crate::validate::ValidatedForm;
+use axum::http::{header, HeaderMap, HeaderName};
+use axum::middleware::from_extractor_with_state;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{
+    extract::{Path, Query, State},
+    Form, Json,
+};
+use axum::{Extension, Router};
+use entity::audit_log::{action, target};
+use entity::blog::{BlogDetail, BlogLink, CombineBlog, Transition};
+use entity::category;
+use entity::sea_orm_active_enums::RoleEnum;
+use entity::user::PublicProfile;
+use entity::{blog, blog_revision, blog_tag, tag};
+use service::query::BlogFilter;
+use service::sea_orm::{prelude::Uuid, ColumnTrait, DatabaseConnection, TryIntoModel};
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(get_blogs, new_blog, new_category, get_categories, new_tag),
-    components(schemas(CombineBlog, blog::Model))
+    paths(
+        get_blogs,
+        get_blog,
+        get_blog_by_slug,
+        new_blog,
+        put_blog,
+        patch_blog,
+        trash_blog,
+        list_trash,
+        restore_blog,
+        purge_blog,
+        submit_blog,
+        approve_blog,
+        reject_blog,
+        review_queue,
+        list_revisions,
+        diff_revisions,
+        rollback_blog,
+        new_category,
+        get_categories,
+        new_tag
+    ),
+    components(schemas(
+        CombineBlog,
+        BlogDetail,
+        BlogLink,
+        PublicProfile,
+        blog::Model,
+        blog_revision::Model,
+        blog_revision::RevisionDiff
+    ))
 )]
 pub(crate) struct ArticleApi;
 
 #[utoipa::path(
     get,
     path = "/get/blogs",
+    params(
+        ("categoryId" = Option<Uuid>, Query, description = "Category including its descendants"),
+        ("tags" = Option<String>, Query, description = "Comma separated tag ids"),
+        ("tagMatch" = Option<String>, Query, description = "any or all"),
+        ("authorId" = Option<Uuid>, Query),
+        ("status" = Option<String>, Query, description = "Draft, Pend, Post or Scheduled"),
+        ("from" = Option<String>, Query, description = "Created at or after"),
+        ("to" = Option<String>, Query, description = "Created before"),
+        ("sort" = Option<String>, Query, description = "createTime, updateTime or title"),
+        ("order" = Option<String>, Query, description = "asc or desc"),
+        ("cursor" = Option<String>, Query, description = "Cursor from a previous page"),
+        ("page" = Option<u64>, Query),
+        ("pageSize" = Option<u64>, Query)
+    ),
     responses(
-        (status = 200, description = "List all blogs successfully", body = [CombineBlog] )
+        (status = 200, description = "List blogs successfully", body = CursorPage<CombineBlog>)
     )
 )]
 pub async fn get_blogs(
     State(db): State<DatabaseConnection>,
-) -> Result<Json<CustomResponse<Vec<CombineBlog>>>> {
-    let list = service::query::Query::get_blog_list(&db).await?;
-    Ok(Json(CustomResponse::ok(list)))
+    claims: Option<Claims>,
+    Query(query): Query<blog::ListQuery>,
+) -> Result<Json<CustomResponse<CursorPage<CombineBlog>>>> {
+    let (page, page_size) = page_params(query.page, query.page_size);
+    let tags = query
+        .tags
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(Uuid::parse_str)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| anyhow::anyhow!("无效的标签ID"))?;
+    let filter = BlogFilter {
+        category_id: query.category_id,
+        tags,
+        tag_match: query.tag_match.unwrap_or_default(),
+        author_id: query.author_id,
+        status: query.status,
+        from: query.from,
+        to: query.to,
+        viewer: claims.map(|i| (i.user_id, i.role)),
+    };
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(decode_cursor::<blog::Cursor>)
+        .transpose()?;
+    let (sort, order) = match &cursor {
+        Some(cursor) => {
+            if query.sort.is_some_and(|i| i != cursor.sort)
+                || query.order.is_some_and(|i| i != cursor.order)
+            {
+                return Err(anyhow::anyhow!("分页游标与排序参数不一致").into());
+            }
+            (cursor.sort, cursor.order)
+        }
+        None => (
+            query.sort.unwrap_or_default(),
+            query.order.unwrap_or_default(),
+        ),
+    };
+    let list = service::query::Query::list_blogs(
+        &db,
+        &filter,
+        sort,
+        order,
+        cursor.as_ref(),
+        page,
+        page_size,
+    )
+    .await?;
+    Ok(Json(CustomResponse::ok(CursorPage {
+        items: list.items,
+        total: list.total,
+        page: cursor.is_none().then_some(page),
+        page_size,
+        next_cursor: list.next.as_ref().map(encode_cursor),
+        prev_cursor: list.prev.as_ref().map(encode_cursor),
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blog/{id}",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Blog with author, category path and neighbours", body = BlogDetail),
+        (status = 404, description = "Blog does not exist or is not visible to the caller")
+    )
+)]
+pub async fn get_blog(
+    State(db): State<DatabaseConnection>,
+    claims: Option<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Tagged<BlogDetail>> {
+    let blog = service::query::Query::get_blog(&db, id)
+        .await?
+        .filter(|blog| can_read(blog, claims.as_ref()))
+        .ok_or(CustomError::NotFound("文章不存在"))?;
+    let etag = etag(&blog);
+    let detail = service::query::Query::get_blog_detail(&db, blog).await?;
+    Ok(([(header::ETAG, etag)], Json(CustomResponse::ok(detail))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blog/slug/{slug}",
+    params(("slug" = String, Path)),
+    responses(
+        (status = 200, description = "Blog with author, category path and neighbours", body = BlogDetail),
+        (status = 308, description = "Old slug, permanently redirects to the current one"),
+        (status = 404, description = "Blog does not exist or is not visible to the caller")
+    )
+)]
+pub async fn get_blog_by_slug(
+    State(db): State<DatabaseConnection>,
+    claims: Option<Claims>,
+    Path(slug): Path<String>,
+) -> Result<Response> {
+    let Some(blog) = service::query::Query::get_blog_by_slug(&db, &slug).await? else {
+        // 不可读的文章不跳转，避免暴露其当前链接
+        let current = service::query::Query::get_slug_redirect(&db, &slug)
+            .await?
+            .filter(|blog| can_read(blog, claims.as_ref()))
+            .ok_or(CustomError::NotFound("文章不存在"))?;
+        // 相对地址，保留路由挂载的前缀
+        return Ok(Redirect::permanent(&current.slug).into_response());
+    };
+    if !can_read(&blog, claims.as_ref()) {
+        return Err(CustomError::NotFound("文章不存在"));
+    }
+    let etag = etag(&blog);
+    let detail = service::query::Query::get_blog_detail(&db, blog).await?;
+    Ok(([(header::ETAG, etag)], Json(CustomResponse::ok(detail))).into_response())
+}
+
+/// Response carrying the blog's version in an `ETag` header.
+type Tagged<T> = ([(HeaderName, String); 1], Json<CustomResponse<T>>);
+
+/// Strong ETag of a blog, `update_time` serves as its version.
+fn etag(blog: &blog::Model) -> String {
+    format!("\"{}\"", blog.update_time.and_utc().timestamp_micros())
+}
+
+#[utoipa::path(
+    put,
+    path = "/blog/{id}",
+    params(("id" = Uuid, Path), ("If-Match" = String, Header, description = "ETag of the edited version")),
+    responses(
+        (status = 200, description = "Blog replaced", body = CombineBlog),
+        (status = 409, description = "Blog was changed since it was read"),
+        (status = 428, description = "If-Match header missing")
+    )
+)]
+pub async fn put_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(form): Json<blog::UpdateModel>,
+) -> Result<Tagged<CombineBlog>> {
+    update_blog(
+        &db,
+        &audit,
+        &claims,
+        id,
+        &headers,
+        form.into(),
+        Edit::Update,
+    )
+    .await
+}
+
+#[utoipa::path(
+    patch,
+    path = "/blog/{id}",
+    params(("id" = Uuid, Path), ("If-Match" = String, Header, description = "ETag of the edited version")),
+    responses(
+        (status = 200, description = "Blog updated", body = CombineBlog),
+        (status = 409, description = "Blog was changed since it was read"),
+        (status = 428, description = "If-Match header missing")
+    )
+)]
+pub async fn patch_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(form): Json<blog::PatchModel>,
+) -> Result<Tagged<CombineBlog>> {
+    update_blog(&db, &audit, &claims, id, &headers, form, Edit::Update).await
+}
+
+/// Where an update comes from, deciding its audit action and revision.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Update,
+    /// Always recorded as a new revision, even when nothing changes.
+    Rollback,
+}
+
+impl Edit {
+    fn action(self) -> &'static str {
+        match self {
+            Edit::Update => action::UPDATE_BLOG,
+            Edit::Rollback => action::ROLLBACK_BLOG,
+        }
+    }
+}
+
+/// 带版本检查的更新：If-Match 必须与当前 ETag 一致
+async fn update_blog(
+    db: &DatabaseConnection,
+    audit: &Audit,
+    claims: &Claims,
+    id: Uuid,
+    headers: &HeaderMap,
+    form: blog::PatchModel,
+    edit: Edit,
+) -> Result<Tagged<CombineBlog>> {
+    let before = service::query::Query::get_blog(db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("文章不存在"))?;
+    if before.user_id != claims.user_id && !claims.has_role(RoleEnum::Editor) {
+        return Err(CustomError::Forbidden);
+    }
+    let if_match = headers
+        .get(header::IF_MATCH)
+        .and_then(|i| i.to_str().ok())
+        .ok_or(CustomError::PreconditionRequired)?;
+    let current = etag(&before);
+    let matches = if_match.trim() == "*"
+        || if_match
+            .split(',')
+            .any(|i| i.trim().trim_start_matches("W/") == current);
+    if !matches {
+        return Err(CustomError::Conflict("Blog was modified by someone else"));
+    }
+    let publish_at = form.publish_at.unwrap_or(before.publish_at);
+    let unpublish_at = form.unpublish_at.unwrap_or(before.unpublish_at);
+    if let Some((publish_at, unpublish_at)) = publish_at.zip(unpublish_at) {
+        if unpublish_at <= publish_at {
+            return Err(anyhow::anyhow!("下线时间必须晚于发布时间").into());
+        }
+    }
+    let slug = match form.slug.as_deref().map(service::slug::slugify) {
+        Some(slug) if !slug.is_empty() && slug != before.slug => {
+            if service::query::Query::slug_taken(db, &slug, Some(id)).await? {
+                return Err(anyhow::anyhow!("文章链接已被占用").into());
+            }
+            Some(slug)
+        }
+        _ => None,
+    };
+    let mut blog = service::mutation::Mutation::update_blog(
+        db,
+        id,
+        before.update_time,
+        form.changes(),
+        form.tags.as_deref(),
+        claims.user_id,
+        edit == Edit::Rollback,
+    )
+    .await?
+    .ok_or(CustomError::Conflict("Blog was modified by someone else"))?;
+    if let Some(slug) = slug {
+        blog = service::mutation::Mutation::change_blog_slug(db, id, &before.slug, &slug).await?;
+    }
+    audit
+        .record(
+            Some(Actor::from(claims)),
+            edit.action(),
+            target::BLOG,
+            blog.id,
+            audit::diff(Some(&before), Some(&blog)),
+        )
+        .await?;
+    let etag = etag(&blog);
+    let blog = service::query::Query::combine_blogs(db, vec![blog])
+        .await?
+        .remove(0);
+    Ok(([(header::ETAG, etag)], Json(CustomResponse::ok(blog))))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/blog/{id}",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Blog moved to the trash", body = blog::Model),
+        (status = 403, description = "Deleting another author's blog")
+    )
+)]
+pub async fn trash_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<blog::Model>>> {
+    let before = service::query::Query::get_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("文章不存在"))?;
+    if before.user_id != claims.user_id && !claims.has_role(RoleEnum::Editor) {
+        return Err(CustomError::Forbidden);
+    }
+    let blog = service::mutation::Mutation::trash_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("文章不存在"))?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::TRASH_BLOG,
+            target::BLOG,
+            blog.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(blog)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blog/trash",
+    params(
+        ("authorId" = Option<Uuid>, Query, description = "Editors may view other authors' trash"),
+        ("page" = Option<u64>, Query),
+        ("pageSize" = Option<u64>, Query)
+    ),
+    responses(
+        (status = 200, description = "Trashed blogs, most recently deleted first", body = Page<blog::Model>)
+    )
+)]
+pub async fn list_trash(
+    State(db): State<DatabaseConnection>,
+    claims: Claims,
+    Query(query): Query<blog::TrashQuery>,
+) -> Result<Json<CustomResponse<Page<blog::Model>>>> {
+    let author_id = query.author_id.unwrap_or(claims.user_id);
+    if author_id != claims.user_id && !claims.has_role(RoleEnum::Editor) {
+        return Err(CustomError::Forbidden);
+    }
+    let (page, page_size) = page_params(query.page, query.page_size);
+    let (items, total) =
+        service::query::Query::list_trashed_blogs(&db, author_id, page, page_size).await?;
+    Ok(Json(CustomResponse::ok(Page {
+        items,
+        total,
+        page,
+        page_size,
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/blog/{id}/restore",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Blog restored from the trash", body = blog::Model)
+    )
+)]
+pub async fn restore_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<blog::Model>>> {
+    let trashed = service::query::Query::get_trashed_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("回收站中没有该文章"))?;
+    if trashed.user_id != claims.user_id && !claims.has_role(RoleEnum::Editor) {
+        return Err(CustomError::Forbidden);
+    }
+    let blog = service::mutation::Mutation::restore_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("回收站中没有该文章"))?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::RESTORE_BLOG,
+            target::BLOG,
+            blog.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(blog)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/blog/{id}/purge",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Trashed blog deleted permanently"),
+        (status = 403, description = "Caller is not an admin")
+    )
+)]
+pub async fn purge_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Admin>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<()>>> {
+    let blog = service::query::Query::get_trashed_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("回收站中没有该文章"))?;
+    service::mutation::Mutation::purge_blogs(&db, blog::Column::Id.eq(id)).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::PURGE_BLOG,
+            target::BLOG,
+            id,
+            audit::diff(Some(&blog), None),
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(())))
+}
+
+#[utoipa::path(
+    post,
+    path = "/blog/{id}/submit",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Draft submitted for review", body = blog::Model),
+        (status = 409, description = "Blog is not a draft")
+    )
+)]
+pub async fn submit_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<blog::Model>>> {
+    let before = service::query::Query::get_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("文章不存在"))?;
+    if before.user_id != claims.user_id && !claims.has_role(RoleEnum::Editor) {
+        return Err(CustomError::Forbidden);
+    }
+    transition_blog(&db, &audit, &claims, before, Transition::Submit, None).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/blog/{id}/approve",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Blog published", body = blog::Model),
+        (status = 409, description = "Blog is not pending review")
+    )
+)]
+pub async fn approve_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Editor>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<blog::Model>>> {
+    let before = service::query::Query::get_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("文章不存在"))?;
+    transition_blog(&db, &audit, &claims, before, Transition::Approve, None).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/blog/{id}/reject",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Blog sent back to its author as a draft", body = blog::Model),
+        (status = 409, description = "Blog is not pending review")
+    )
+)]
+pub async fn reject_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Editor>,
+    Path(id): Path<Uuid>,
+    ValidatedForm(form): ValidatedForm<blog::RejectModel>,
+) -> Result<Json<CustomResponse<blog::Model>>> {
+    let before = service::query::Query::get_blog(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("文章不存在"))?;
+    transition_blog(
+        &db,
+        &audit,
+        &claims,
+        before,
+        Transition::Reject,
+        Some(form.note),
+    )
+    .await
+}
+
+async fn transition_blog(
+    db: &DatabaseConnection,
+    audit: &Audit,
+    claims: &Claims,
+    before: blog::Model,
+    transition: Transition,
+    note: Option<String>,
+) -> Result<Json<CustomResponse<blog::Model>>> {
+    let (from, _) = transition.statuses();
+    if before.current_status() != from {
+        return Err(CustomError::Conflict(transition.invalid_message()));
+    }
+    let now = chrono::Utc::now().naive_utc();
+    if transition == Transition::Approve && before.unpublish_at.is_some_and(|at| at <= now) {
+        return Err(CustomError::Conflict("Unpublish time has already passed"));
+    }
+    let blog = service::mutation::Mutation::transition_blog(
+        db,
+        before.id,
+        transition,
+        claims.user_id,
+        note,
+    )
+    .await?
+    .ok_or(CustomError::Conflict(transition.invalid_message()))?;
+    let action = match transition {
+        Transition::Submit => action::SUBMIT_BLOG,
+        Transition::Approve => action::APPROVE_BLOG,
+        Transition::Reject => action::REJECT_BLOG,
+    };
+    audit
+        .record(
+            Some(Actor::from(claims)),
+            action,
+            target::BLOG,
+            blog.id,
+            audit::diff(Some(&before), Some(&blog)),
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(blog)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blog/review-queue",
+    params(("page" = Option<u64>, Query), ("pageSize" = Option<u64>, Query)),
+    responses(
+        (status = 200, description = "Blogs pending review, oldest submission first", body = Page<CombineBlog>)
+    )
+)]
+pub async fn review_queue(
+    State(db): State<DatabaseConnection>,
+    Query(query): Query<blog::ReviewQueueQuery>,
+) -> Result<Json<CustomResponse<Page<CombineBlog>>>> {
+    let (page, page_size) = page_params(query.page, query.page_size);
+    let (items, total) = service::query::Query::review_queue(&db, page, page_size).await?;
+    Ok(Json(CustomResponse::ok(Page {
+        items,
+        total,
+        page,
+        page_size,
+    })))
+}
+
+/// 历史版本只对作者本人和编辑以上角色可见
+async fn revision_blog(db: &DatabaseConnection, id: Uuid, claims: &Claims) -> Result<blog::Model> {
+    let blog = service::query::Query::get_blog(db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("文章不存在"))?;
+    if blog.user_id != claims.user_id && !claims.has_role(RoleEnum::Editor) {
+        return Err(CustomError::Forbidden);
+    }
+    Ok(blog)
+}
+
+#[utoipa::path(
+    get,
+    path = "/blog/{id}/revisions",
+    params(("id" = Uuid, Path), ("page" = Option<u64>, Query), ("pageSize" = Option<u64>, Query)),
+    responses(
+        (status = 200, description = "Revisions of the blog, newest first", body = Page<blog_revision::Model>),
+        (status = 403, description = "Viewing another author's revisions")
+    )
+)]
+pub async fn list_revisions(
+    State(db): State<DatabaseConnection>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Query(query): Query<blog_revision::ListQuery>,
+) -> Result<Json<CustomResponse<Page<blog_revision::Model>>>> {
+    revision_blog(&db, id, &claims).await?;
+    let (page, page_size) = page_params(query.page, query.page_size);
+    let (items, total) = service::query::Query::list_revisions(&db, id, page, page_size).await?;
+    Ok(Json(CustomResponse::ok(Page {
+        items,
+        total,
+        page,
+        page_size,
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/blog/{id}/revisions/diff",
+    params(
+        ("id" = Uuid, Path),
+        ("from" = Uuid, Query),
+        ("to" = Uuid, Query),
+        ("mode" = Option<String>, Query, description = "line or word")
+    ),
+    responses(
+        (status = 200, description = "Changes from one revision to another", body = blog_revision::RevisionDiff)
+    )
+)]
+pub async fn diff_revisions(
+    State(db): State<DatabaseConnection>,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+    Query(query): Query<blog_revision::DiffQuery>,
+) -> Result<Json<CustomResponse<blog_revision::RevisionDiff>>> {
+    revision_blog(&db, id, &claims).await?;
+    let from = service::query::Query::get_revision(&db, id, query.from)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("版本不存在"))?;
+    let to = service::query::Query::get_revision(&db, id, query.to)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("版本不存在"))?;
+    let diff = service::diff::revisions(&from, &to, query.mode.unwrap_or_default());
+    Ok(Json(CustomResponse::ok(diff)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/blog/{id}/revisions/{rev}/rollback",
+    params(
+        ("id" = Uuid, Path),
+        ("rev" = Uuid, Path),
+        ("If-Match" = String, Header, description = "ETag of the current version")
+    ),
+    responses(
+        (status = 200, description = "Blog restored to the revision, recorded as a new revision", body = CombineBlog),
+        (status = 409, description = "Blog was changed since it was read"),
+        (status = 428, description = "If-Match header missing")
+    )
+)]
+pub async fn rollback_blog(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Path((id, rev)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> Result<Tagged<CombineBlog>> {
+    let revision = service::query::Query::get_revision(&db, id, rev)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("版本不存在"))?;
+    update_blog(
+        &db,
+        &audit,
+        &claims,
+        id,
+        &headers,
+        revision.patch(),
+        Edit::Rollback,
+    )
+    .await
+}
+
+/// 未发布的文章只对作者本人和编辑以上角色可见
+fn can_read(blog: &blog::Model, claims: Option<&Claims>) -> bool {
+    blog.is_published(chrono::Utc::now().naive_utc())
+        || claims.is_some_and(|claims| {
+            claims.user_id == blog.user_id || claims.has_role(RoleEnum::Editor)
+        })
 }
 
 #[utoipa::path(
     post,
     path = "/blog/new",
+    params(("If-Match" = Option<String>, Header, description = "ETag of the edited version, required when `id` is set")),
     responses(
-        (status = 200, description = "New Blog", body = [CombineBlog])
+        (status = 200, description = "New Blog", body = [CombineBlog]),
+        (status = 403, description = "Editing another author's blog"),
+        (status = 409, description = "Blog was changed since it was read"),
+        (status = 428, description = "If-Match header missing")
     )
 )]
 pub async fn new_blog(
     State(db): State<DatabaseConnection>,
+    audit: Audit,
     claims: Claims,
-    Json(form): Json<blog::ReqModel>,
-) -> Result<Json<CustomResponse<CombineBlog>>> {
+    headers: HeaderMap,
+    Json(mut form): Json<blog::ReqModel>,
+) -> Result<Tagged<CombineBlog>> {
+    // 修改已有文章与 PUT 相同，需要 If-Match 防止覆盖他人的修改
+    if let Some(id) = form.id.take() {
+        let id = Uuid::parse_str(&id).map_err(|_| anyhow::anyhow!("无效的文章ID"))?;
+        return update_blog(
+            &db,
+            &audit,
+            &claims,
+            id,
+            &headers,
+            form.into(),
+            Edit::Update,
+        )
+        .await;
+    }
+    // 手动指定的链接不能与其他文章冲突，未指定时根据标题生成
+    let slug = match form.slug.as_deref().map(service::slug::slugify) {
+        Some(slug) if !slug.is_empty() => {
+            if service::query::Query::slug_taken(&db, &slug, None).await? {
+                return Err(anyhow::anyhow!("文章链接已被占用").into());
+            }
+            slug
+        }
+        _ => {
+            service::query::Query::unique_blog_slug(&db, &service::slug::slugify(&form.title), None)
+                .await?
+        }
+    };
     let insert_form = blog::InsertModel {
         user_id: claims.user_id,
-        id: form.id,
+        id: None,
+        slug: Some(slug),
         title: form.title,
         content: form.content,
         category_id: form.category_id,
         cover_image: form.cover_image,
     };
-    let data = service::mutation::Mutation::create_blog(&db, insert_form.into()).await?;
-    let blog_model = data.try_into_model().unwrap();
-    let blog_tag_list: Vec<Uuid> = service::query::Query::get_tag_list(&db, blog_model.id)
-        .await?
-        .into_iter()
-        .map(|i| i.id)
-        .collect();
-
-    println!("{:?}", blog_tag_list);
-    for blog_tag in blog_tag_list.iter() {
-        if !form.tags.contains(blog_tag) {
-            service::mutation::Mutation::delete_blog_tag(&db, *blog_tag).await?;
-        }
-    }
-    for tag_id in form.tags.iter() {
-        if !blog_tag_list.contains(tag_id) {
-            let blog_tag = blog_tag::Model {
-                blog_id: blog_model.id,
-                tag_id: *tag_id,
-            };
-            service::mutation::Mutation::create_blog_tag(&db, blog_tag.into()).await?;
+    let data =
+        service::mutation::Mutation::create_blog(&db, blog::ActiveModel::from(insert_form)).await?;
+    let blog_model = data.try_into_model()?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::CREATE_BLOG,
+            target::BLOG,
+            blog_model.id,
+            audit::diff(None, Some(&blog_model)),
+        )
+        .await?;
+    let mut tags = form.tags;
+    tags.sort();
+    tags.dedup();
+    for tag_id in tags {
+        let blog_tag = blog_tag::Model {
+            blog_id: blog_model.id,
+            tag_id,
         };
+        service::mutation::Mutation::create_blog_tag(&db, blog_tag.into()).await?;
     }
-    let tags = service::query::Query::get_tag_list(&db, blog_model.id)
-        .await?
-        .into_iter()
-        .map(|i| i.name.unwrap())
-        .collect();
-    let category = service::query::Query::query_blog_category(&db, blog_model.category_id).await?;
-    Ok(Json(CustomResponse::ok(CombineBlog {
-        blog: blog_model,
-        tags,
-        category: category.unwrap().name,
-    })))
+    service::mutation::Mutation::record_revision(&db, &blog_model, claims.user_id, false).await?;
+    let etag = etag(&blog_model);
+    let blog = service::query::Query::combine_blogs(&db, vec![blog_model])
+        .await?
+        .remove(0);
+    Ok(([(header::ETAG, etag)], Json(CustomResponse::ok(blog))))
 }
 
 #[utoipa::path(
@@ -95,11 +829,23 @@ pub async fn new_blog(
 )]
 pub async fn new_category(
     State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Editor>,
     form: Form<category::InsertModel>,
 ) -> Result<Json<CustomResponse<category::Model>>> {
     let form = form.0;
     let data = service::mutation::Mutation::create_category(&db, form.into()).await?;
-    Ok(Json(CustomResponse::ok(data.try_into_model().unwrap())))
+    let category = data.try_into_model().unwrap();
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::CREATE_CATEGORY,
+            target::CATEGORY,
+            category.id,
+            audit::diff(None, Some(&category)),
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(category)))
 }
 
 #[utoipa::path(
@@ -125,21 +871,58 @@ pub async fn get_categories(
 )]
 pub async fn new_tag(
     State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Editor>,
     Form(form): Form<tag::InsertModel>,
 ) -> Result<Json<CustomResponse<tag::Model>>> {
     let data = service::mutation::Mutation::create_tag(&db, form.into()).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::CREATE_TAG,
+            target::TAG,
+            data.id,
+            audit::diff(None, Some(&data)),
+        )
+        .await?;
     Ok(Json(CustomResponse::ok(data)))
 }
 
-pub fn route() -> Router<DatabaseConnection> {
+pub fn route(state: AppState) -> Router<AppState> {
     let auth_route = Router::new()
         .route("/blog/new", post(new_blog))
-        .route_layer(from_extractor::<Claims>());
-    let router = Router::new()
-        .merge(auth_route)
-        .route("/get/blogs", get(get_blogs))
+        .route(
+            "/blog/:id",
+            put(put_blog).patch(patch_blog).delete(trash_blog),
+        )
+        .route("/blog/:id/restore", post(restore_blog))
+        .route("/blog/:id/submit", post(submit_blog))
+        .route("/blog/:id/revisions/:rev/rollback", post(rollback_blog))
+        .route("/blog/trash", get(list_trash))
+        .route_layer(from_extractor_with_state::<RequireRole<Author>, _>(
+            state.clone(),
+        ))
+        .route_layer(Extension(Scope::BlogWrite));
+    let admin_route = Router::new()
+        .route("/blog/:id/purge", delete(purge_blog))
+        .route_layer(from_extractor_with_state::<RequireRole<Admin>, _>(
+            state.clone(),
+        ));
+    let editor_route = Router::new()
         .route("/tag/new", post(new_tag))
         .route("/category/new", post(new_category))
-        .route("/category/list", get(get_categories));
-    router
+        .route("/blog/:id/approve", post(approve_blog))
+        .route("/blog/:id/reject", post(reject_blog))
+        .route("/blog/review-queue", get(review_queue))
+        .route_layer(from_extractor_with_state::<RequireRole<Editor>, _>(state));
+    Router::new()
+        .merge(auth_route)
+        .merge(editor_route)
+        .merge(admin_route)
+        .route("/get/blogs", get(get_blogs))
+        .route("/blog/:id", get(get_blog))
+        .route("/blog/slug/:slug", get(get_blog_by_slug))
+        .route("/blog/:id/revisions", get(list_revisions))
+        .route("/blog/:id/revisions/diff", get(diff_revisions))
+        .route("/category/list", get(get_categories))
 }
@@ -0,0 +1,31 @@
+use crate::auth::{Admin, RequireRole};
+use crate::response::{page_params, CustomResponse, Page, Result};
+use crate::state::AppState;
+use axum::extract::{Query, State};
+use axum::middleware::from_extractor_with_state;
+use axum::routing::get;
+use axum::{Json, Router};
+use entity::audit_log;
+use service::sea_orm::DatabaseConnection;
+
+/// Audit records matching the filters, newest first.
+pub async fn list_audit_logs(
+    State(db): State<DatabaseConnection>,
+    Query(query): Query<audit_log::QueryModel>,
+) -> Result<Json<CustomResponse<Page<audit_log::Model>>>> {
+    let (page, page_size) = page_params(query.page, query.page_size);
+    let (items, total) =
+        service::query::Query::list_audit_logs(&db, query, page, page_size).await?;
+    Ok(Json(CustomResponse::ok(Page {
+        items,
+        total,
+        page,
+        page_size,
+    })))
+}
+
+pub fn route(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/audit/logs", get(list_audit_logs))
+        .route_layer(from_extractor_with_state::<RequireRole<Admin>, _>(state))
+}
@@ -1,3 +1,5 @@
 pub mod article;
+pub mod audit;
+pub mod oidc;
 pub mod user;
 pub mod upload;
\ No newline at end of file
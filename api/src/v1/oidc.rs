@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use crate::audit::{Actor, Audit};
+use crate::auth::{self, Keys, LoginResult};
+use crate::config::Config;
+use crate::cookie;
+use crate::error::CustomError;
+use crate::oidc::{IdTokenClaims, OidcClient};
+use crate::response::{CustomResponse, Result};
+use crate::state::AppState;
+use axum::extract::{Query, State};
+use axum::response::Redirect;
+use axum::routing::get;
+use axum::{Json, Router};
+use entity::audit_log::{action, target};
+use entity::{oidc_login, user};
+use service::sea_orm::{ActiveValue::Set, DatabaseConnection};
+use tower_cookies::Cookies;
+
+/// 授权请求的有效期（秒）
+const LOGIN_TTL: i64 = 10 * 60;
+
+fn client(oidc: Option<Arc<OidcClient>>) -> Result<Arc<OidcClient>> {
+    oidc.ok_or_else(|| anyhow::anyhow!("未启用 OIDC 登录").into())
+}
+
+/// Redirects the browser to the identity provider.
+pub async fn oidc_login(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(oidc): State<Option<Arc<OidcClient>>>,
+    cookies: Cookies,
+) -> Result<Redirect> {
+    let oidc = client(oidc)?;
+    let login = oidc_login::Model {
+        state: service::token::generate(),
+        nonce: service::token::generate(),
+        code_verifier: service::token::generate(),
+        expires_at: (chrono::Utc::now() + chrono::Duration::seconds(LOGIN_TTL)).naive_utc(),
+    };
+    // S256: BASE64URL(SHA256(code_verifier))，与 token::hash 的算法一致
+    let code_challenge = service::token::hash(&login.code_verifier);
+    let url = oidc
+        .authorization_url(&login.state, &login.nonce, &code_challenge)
+        .await?;
+    cookie::set_oidc_state(&cookies, &config.cookie, &login.state, LOGIN_TTL);
+    service::mutation::Mutation::create_oidc_login(&db, login).await?;
+    Ok(Redirect::to(url.as_str()))
+}
+
+/// Completes the provider login and issues the blog's own tokens, or an mfa
+/// token when the account has TOTP enabled.
+pub async fn oidc_callback(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    State(oidc): State<Option<Arc<OidcClient>>>,
+    cookies: Cookies,
+    audit: Audit,
+    Query(query): Query<user::OidcCallbackModel>,
+) -> Result<Json<CustomResponse<LoginResult>>> {
+    let oidc = client(oidc)?;
+    // state 必须来自发起登录的同一浏览器，防止攻击者把自己的回调地址发给受害者
+    if !cookie::take_oidc_state(&cookies, &config.cookie, &query.state) {
+        return Err(CustomError::Unauthorized(
+            "Login was not started in this browser",
+        ));
+    }
+    let login = service::mutation::Mutation::take_oidc_login(&db, &query.state)
+        .await?
+        .ok_or(CustomError::Unauthorized(
+            "Unknown or expired login request",
+        ))?;
+    if let Some(error) = query.error {
+        let description = query.error_description.unwrap_or_default();
+        return Err(anyhow::anyhow!("身份提供方拒绝登录：{error} {description}").into());
+    }
+    let code = query.code.ok_or_else(|| anyhow::anyhow!("缺少授权码"))?;
+    let claims = oidc
+        .exchange_code(&code, &login.code_verifier, &login.nonce)
+        .await
+        .map_err(|e| {
+            tracing::warn!(error = format!("{e:#}"), "oidc login failed");
+            CustomError::Unauthorized("Identity provider login failed")
+        })?;
+    let user = find_or_create_user(&db, &config, oidc.issuer(), claims).await?;
+    if user.totp_enabled_at.is_some() {
+        let challenge = auth::mfa_challenge(&db, &config, &keys, user.id).await?;
+        return Ok(Json(CustomResponse::ok(LoginResult::MfaRequired(
+            challenge,
+        ))));
+    }
+    let tokens = auth::issue_tokens(&db, &config, &keys, &user).await?;
+    cookie::set_session(&cookies, &config, &tokens);
+    audit
+        .record(
+            Some(Actor::from(&user)),
+            action::LOGIN,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(LoginResult::Tokens(tokens))))
+}
+
+/// 按已关联的身份查找用户；否则按已验证的邮箱关联现有用户，都没有时新建用户
+async fn find_or_create_user(
+    db: &DatabaseConnection,
+    config: &Config,
+    issuer: &str,
+    claims: IdTokenClaims,
+) -> Result<user::Model> {
+    if let Some(user) = service::query::Query::get_user_by_identity(db, issuer, &claims.sub).await?
+    {
+        return Ok(user);
+    }
+    let email = claims
+        .email
+        .ok_or_else(|| anyhow::anyhow!("身份提供方未返回邮箱"))?;
+    // 未经提供方验证的邮箱不能用来关联已有账号，否则可以冒领他人账号
+    if claims.email_verified {
+        let mut users = service::query::Query::get_users_by_email(db, &email).await?;
+        if users.len() > 1 {
+            return Err(anyhow::anyhow!("该邮箱对应多个账号，无法自动关联").into());
+        }
+        if let Some(user) = users.pop() {
+            service::mutation::Mutation::link_identity(db, user.id, issuer, &claims.sub).await?;
+            if user.email_verified_at.is_none() {
+                return Ok(service::mutation::Mutation::mark_email_verified(db, user.id).await?);
+            }
+            return Ok(user);
+        }
+    }
+    let base = claims
+        .preferred_username
+        .or_else(|| email.split('@').next().map(str::to_string))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| claims.sub.clone());
+    let username = unique_username(db, &base).await?;
+    // 随机密码，用户需要通过重置密码才能使用密码登录
+    let password = config.password.hash(&service::token::generate())?;
+    let mut form: user::ActiveModel = user::InsertModel {
+        username,
+        password,
+        email,
+    }
+    .into();
+    if claims.email_verified {
+        form.email_verified_at = Set(Some(chrono::Utc::now().naive_utc()));
+    }
+    Ok(
+        service::mutation::Mutation::create_user_with_identity(db, form, issuer, &claims.sub)
+            .await?,
+    )
+}
+
+async fn unique_username(db: &DatabaseConnection, base: &str) -> Result<String> {
+    let mut username = base.to_string();
+    for n in 2..100 {
+        if service::query::Query::get_user_by_username(db, &username)
+            .await?
+            .is_none()
+        {
+            return Ok(username);
+        }
+        username = format!("{base}{n}");
+    }
+    Ok(format!("{base}-{}", &service::token::generate()[..8]))
+}
+
+pub fn route() -> Router<AppState> {
+    Router::new()
+        .route("/user/oidc/login", get(oidc_login))
+        .route("/user/oidc/callback", get(oidc_callback))
+}
@@ -1,16 +1,32 @@
-use crate::{response::Result, util::stream_to_file};
-use axum::{extract::Multipart, routing::get, Router};
-use service::sea_orm::DatabaseConnection;
+use crate::{
+    audit::{Actor, Audit},
+    auth::{Claims, Scope},
+    response::Result,
+    state::AppState,
+    util::stream_to_file,
+};
+use axum::{extract::Multipart, routing::get, Extension, Router};
+use entity::audit_log::{action, target};
 
-pub async fn upload_file(mut multipart: Multipart) -> Result<()> {
+pub async fn upload_file(audit: Audit, claims: Claims, mut multipart: Multipart) -> Result<()> {
     while let Ok(Some(field)) = multipart.next_field().await {
         let name = field.file_name().unwrap().to_string();
         stream_to_file(&name, field).await?;
+        audit
+            .record(
+                Some(Actor::from(&claims)),
+                action::UPLOAD,
+                target::FILE,
+                &name,
+                None,
+            )
+            .await?;
     }
     Ok(())
 }
 
-pub fn route() -> Router<DatabaseConnection> {
-    let router = Router::new().route("/upload", get(upload_file));
-    router
+pub fn route() -> Router<AppState> {
+    Router::new()
+        .route("/upload", get(upload_file))
+        .route_layer(Extension(Scope::Upload))
 }
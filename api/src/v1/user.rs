@@ -1,49 +1,909 @@
+use std::sync::Arc;
+
+use crate::audit::{self, Actor, Audit};
+use crate::auth;
+use crate::auth::Admin;
 use crate::auth::Claims;
 use crate::auth::Keys;
-use crate::response::CustomResponse;
+use crate::auth::LoginResult;
+use crate::auth::RequireRole;
+use crate::auth::Scope;
+use crate::auth::TokenPair;
+use crate::auth::ACCESS_TOKEN_PREFIX;
+use crate::client::ClientIp;
+use crate::config::Config;
+use crate::cookie;
+use crate::error::CustomError;
+use crate::mail::{Mail, MailTransport};
 use crate::response::Result;
+use crate::response::{page_params, CustomResponse, Page};
+use crate::state::AppState;
+use crate::throttle;
+use crate::util::{image_extension, stream_to_file};
 use crate::validate::ValidatedForm;
+use axum::body::Bytes;
+use axum::extract::multipart::MultipartError;
+use axum::extract::Multipart;
+use axum::extract::Path;
+use axum::extract::Query;
 use axum::extract::State;
-use axum::routing::post;
+use axum::http::{HeaderMap, Method};
+use axum::middleware::from_extractor_with_state;
+use axum::routing::delete;
+use axum::routing::{get, post};
+use axum::Extension;
 use axum::Form;
 use axum::Json;
 use axum::Router;
-use entity::user;
-use jsonwebtoken::encode;
-use jsonwebtoken::Header;
+use entity::audit_log::{action, target};
+use entity::{access_token, user, user_token};
+use futures::{stream, StreamExt};
+use jsonwebtoken::jwk::JwkSet;
+use service::password::Verification;
 use service::sea_orm::prelude::Uuid;
 use service::sea_orm::DatabaseConnection;
+use tower_cookies::Cookies;
 
 pub async fn new_user(
     State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    State(mailer): State<Arc<dyn MailTransport>>,
+    audit: Audit,
     ValidatedForm(form): ValidatedForm<user::InsertModel>,
 ) -> Result<Json<CustomResponse<Uuid>>> {
-    let data = service::mutation::Mutation::create_user(&db, form.into()).await?;
+    let data = service::mutation::Mutation::create_user(&db, &config.password, form).await?;
+    let profile = user::Profile::from(data.clone());
+    audit
+        .record(
+            Some(Actor::from(&data)),
+            action::REGISTER,
+            target::USER,
+            data.id,
+            audit::diff(None, Some(&profile)),
+        )
+        .await?;
+    // 邮件发送失败不影响注册，用户可以稍后重新发送
+    if let Err(err) = send_verification(&db, &config, &keys, mailer.as_ref(), &data).await {
+        tracing::warn!(user_id = %data.id, error = %err, "failed to send verification mail");
+    }
     Ok(Json(CustomResponse::ok(data.id)))
 }
 
+async fn send_verification(
+    db: &DatabaseConnection,
+    config: &Config,
+    keys: &Keys,
+    mailer: &dyn MailTransport,
+    user: &user::Model,
+) -> Result<()> {
+    let token = auth::issue_action_token(
+        db,
+        keys,
+        user.id,
+        user_token::purpose::VERIFY_EMAIL,
+        config.verify_email_ttl,
+    )
+    .await?;
+    mailer
+        .send(Mail {
+            to: user.email.clone(),
+            subject: "验证你的邮箱".to_string(),
+            body: format!(
+                "{}，你好：\n\n请打开以下链接完成邮箱验证：\n{}/user/verify?token={}\n",
+                user.username, config.public_url, token
+            ),
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn verify_email(
+    State(db): State<DatabaseConnection>,
+    State(keys): State<Arc<Keys>>,
+    audit: Audit,
+    Query(query): Query<user::TokenModel>,
+) -> Result<Json<CustomResponse<Uuid>>> {
+    let user_id =
+        auth::consume_action_token(&db, &keys, &query.token, user_token::purpose::VERIFY_EMAIL)
+            .await?;
+    let user = service::mutation::Mutation::mark_email_verified(&db, user_id).await?;
+    audit
+        .record(
+            Some(Actor::from(&user)),
+            action::VERIFY_EMAIL,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(user.id)))
+}
+
+pub async fn resend_verification(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    State(mailer): State<Arc<dyn MailTransport>>,
+    claims: Claims,
+) -> Result<Json<CustomResponse<()>>> {
+    let user = current_user(&db, &claims).await?;
+    if user.email_verified_at.is_some() {
+        return Err(anyhow::anyhow!("邮箱已验证").into());
+    }
+    send_verification(&db, &config, &keys, mailer.as_ref(), &user).await?;
+    Ok(Json(CustomResponse::ok(())))
+}
+
 pub async fn login_in(
     State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    ClientIp(ip): ClientIp,
+    cookies: Cookies,
+    audit: Audit,
     form: Form<user::LoginModel>,
-) -> Result<Json<CustomResponse<String>>> {
+) -> Result<Json<CustomResponse<LoginResult>>> {
     let form = form.0;
-    let user = service::query::Query::check_user_exist(&db, form).await?;
+    let username = form.username.clone();
+    throttle::check(&db, &config.login_throttle, &username, ip).await?;
+    let user = service::mutation::Mutation::check_user_exist(&db, &config.password, form).await?;
     if let Some(user) = user {
-        let claims = Claims {
-            name: user.username,
-            exp: chrono::Utc::now().timestamp() + 60 * 60 * 1000,
-            user_id: user.id,
-        };
-        let token = encode(&Header::default(), &claims, &Keys::new(b"test").encoding)?;
-        Ok(Json(CustomResponse::ok(token)))
+        throttle::release(&db, &username, ip).await?;
+        // 启用两步验证时失败计数保留到验证码通过
+        if user.totp_enabled_at.is_some() {
+            let challenge = auth::mfa_challenge(&db, &config, &keys, user.id).await?;
+            return Ok(Json(CustomResponse::ok(LoginResult::MfaRequired(
+                challenge,
+            ))));
+        }
+        throttle::reset(&db, &username).await?;
+        let tokens = auth::issue_tokens(&db, &config, &keys, &user).await?;
+        cookie::set_session(&cookies, &config, &tokens);
+        audit
+            .record(
+                Some(Actor::from(&user)),
+                action::LOGIN,
+                target::USER,
+                user.id,
+                None,
+            )
+            .await?;
+        Ok(Json(CustomResponse::ok(LoginResult::Tokens(tokens))))
     } else {
+        throttle::record_failure(&db, &config.login_throttle, &username, ip).await?;
+        audit
+            .record(None, action::LOGIN_FAILED, target::USER, &username, None)
+            .await?;
         Err(anyhow::anyhow!("未找到该用户").into())
     }
 }
 
-pub fn route() -> Router<DatabaseConnection> {
-    let router = Router::new()
+/// Second login step: exchanges the mfa token and a TOTP or recovery code for tokens.
+pub async fn login_mfa(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    ClientIp(ip): ClientIp,
+    cookies: Cookies,
+    audit: Audit,
+    Form(form): Form<user::MfaLoginModel>,
+) -> Result<Json<CustomResponse<TokenPair>>> {
+    let user_id = auth::action_token_user(&keys, &form.mfa_token, user_token::purpose::MFA)?;
+    let user = service::query::Query::get_user(&db, user_id)
+        .await?
+        .ok_or(CustomError::Unauthorized("User not found"))?;
+    throttle::check(&db, &config.login_throttle, &user.username, ip).await?;
+    if !check_second_factor(&db, &user, form.code.trim()).await? {
+        throttle::record_failure(&db, &config.login_throttle, &user.username, ip).await?;
+        audit
+            .record(
+                None,
+                action::LOGIN_FAILED,
+                target::USER,
+                &user.username,
+                None,
+            )
+            .await?;
+        return Err(anyhow::anyhow!("验证码错误").into());
+    }
+    throttle::release(&db, &user.username, ip).await?;
+    auth::consume_action_token(&db, &keys, &form.mfa_token, user_token::purpose::MFA).await?;
+    throttle::reset(&db, &user.username).await?;
+    let tokens = auth::issue_tokens(&db, &config, &keys, &user).await?;
+    cookie::set_session(&cookies, &config, &tokens);
+    audit
+        .record(
+            Some(Actor::from(&user)),
+            action::LOGIN,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(tokens)))
+}
+
+/// 校验 TOTP 验证码，或使用一次恢复码
+async fn check_second_factor(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    code: &str,
+) -> Result<bool> {
+    let Some(secret) = user
+        .totp_secret
+        .as_deref()
+        .filter(|_| user.totp_enabled_at.is_some())
+    else {
+        return Ok(false);
+    };
+    if code.len() == 6 && code.bytes().all(|b| b.is_ascii_digit()) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let Some(step) = service::totp::verify(secret, code, now, user.totp_last_step) else {
+            return Ok(false);
+        };
+        return Ok(service::mutation::Mutation::use_totp_step(db, user.id, step).await?);
+    }
+    let hash = service::token::hash(&code.to_lowercase());
+    Ok(service::mutation::Mutation::consume_recovery_code(db, user.id, &hash).await?)
+}
+
+pub async fn refresh(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    method: Method,
+    form: Option<Form<user::RefreshModel>>,
+) -> Result<Json<CustomResponse<TokenPair>>> {
+    let refresh_token = match form {
+        Some(Form(form)) => form.refresh_token,
+        // 浏览器客户端从 Cookie 中读取刷新令牌，同样需要通过 CSRF 校验
+        None => {
+            let token = cookies
+                .get(cookie::REFRESH_COOKIE)
+                .filter(|_| config.cookie.enabled)
+                .ok_or_else(|| anyhow::anyhow!("缺少刷新令牌"))?;
+            if !cookie::check_csrf(&method, &headers, &cookies) {
+                return Err(CustomError::Csrf);
+            }
+            token.value().to_string()
+        }
+    };
+    let tokens = auth::refresh_tokens(&db, &config, &keys, &refresh_token).await?;
+    cookie::set_session(&cookies, &config, &tokens);
+    Ok(Json(CustomResponse::ok(tokens)))
+}
+
+pub async fn logout(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    cookies: Cookies,
+    audit: Audit,
+    claims: Claims,
+) -> Result<Json<CustomResponse<u64>>> {
+    let count = service::mutation::Mutation::revoke_session(&db, claims.sid).await?;
+    cookie::clear_session(&cookies, &config.cookie);
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::LOGOUT,
+            target::USER,
+            claims.user_id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(count)))
+}
+
+/// Revokes every session of the caller, including the current one.
+pub async fn logout_all(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    cookies: Cookies,
+    audit: Audit,
+    claims: Claims,
+) -> Result<Json<CustomResponse<u64>>> {
+    let count = service::mutation::Mutation::revoke_user_sessions(&db, claims.user_id).await?;
+    cookie::clear_session(&cookies, &config.cookie);
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::LOGOUT,
+            target::USER,
+            claims.user_id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(count)))
+}
+
+async fn current_user(db: &DatabaseConnection, claims: &Claims) -> Result<user::Model> {
+    service::query::Query::get_user(db, claims.user_id)
+        .await?
+        .ok_or(CustomError::Unauthorized("User not found"))
+}
+
+pub async fn get_profile(
+    State(db): State<DatabaseConnection>,
+    claims: Claims,
+) -> Result<Json<CustomResponse<user::Profile>>> {
+    let user = current_user(&db, &claims).await?;
+    Ok(Json(CustomResponse::ok(user.into())))
+}
+
+pub async fn update_profile(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    State(mailer): State<Arc<dyn MailTransport>>,
+    audit: Audit,
+    claims: Claims,
+    ValidatedForm(mut form): ValidatedForm<user::UpdateProfileModel>,
+) -> Result<Json<CustomResponse<user::Profile>>> {
+    let user = current_user(&db, &claims).await?;
+    if form.email.as_ref() == Some(&user.email) {
+        form.email = None;
+    }
+    let email_changed = form.email.is_some();
+    let before = user::Profile::from(user);
+    let user = service::mutation::Mutation::update_user_profile(&db, before.id, form).await?;
+    let after = user::Profile::from(user.clone());
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::UPDATE_PROFILE,
+            target::USER,
+            user.id,
+            audit::diff(Some(&before), Some(&after)),
+        )
+        .await?;
+    // 修改邮箱后需要重新验证
+    if email_changed {
+        if let Err(err) = send_verification(&db, &config, &keys, mailer.as_ref(), &user).await {
+            tracing::warn!(user_id = %user.id, error = %err, "failed to send verification mail");
+        }
+    }
+    Ok(Json(CustomResponse::ok(user.into())))
+}
+
+/// Changes the password after checking the current one. Other sessions are
+/// logged out, the current one stays valid.
+pub async fn change_password(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    audit: Audit,
+    claims: Claims,
+    ValidatedForm(form): ValidatedForm<user::ChangePasswordModel>,
+) -> Result<Json<CustomResponse<()>>> {
+    let user = current_user(&db, &claims).await?;
+    check_password(&config, &user, &form.current_password)?;
+    let hash = config.password.hash(&form.new_password)?;
+    service::mutation::Mutation::update_user_password(&db, user.id, hash).await?;
+    service::mutation::Mutation::revoke_other_sessions(&db, user.id, claims.sid).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::CHANGE_PASSWORD,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(())))
+}
+
+/// Starts TOTP enrollment; the secret only takes effect once a code is confirmed.
+pub async fn enroll_totp(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    claims: Claims,
+) -> Result<Json<CustomResponse<user::TotpEnrollment>>> {
+    let user = current_user(&db, &claims).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(anyhow::anyhow!("已启用两步验证").into());
+    }
+    let secret = service::totp::generate_secret();
+    let otpauth_uri = service::totp::otpauth_uri(&secret, &config.totp_issuer, &user.username)?;
+    service::mutation::Mutation::set_totp_secret(&db, user.id, secret.clone()).await?;
+    Ok(Json(CustomResponse::ok(user::TotpEnrollment {
+        secret,
+        otpauth_uri,
+    })))
+}
+
+/// Enables TOTP after checking a code from the app and returns fresh recovery codes.
+pub async fn confirm_totp(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Form(form): Form<user::TotpCodeModel>,
+) -> Result<Json<CustomResponse<Vec<String>>>> {
+    let user = current_user(&db, &claims).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(anyhow::anyhow!("已启用两步验证").into());
+    }
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("请先生成两步验证密钥"))?;
+    let now = chrono::Utc::now().timestamp() as u64;
+    let step = service::totp::verify(secret, form.code.trim(), now, None)
+        .ok_or_else(|| anyhow::anyhow!("验证码错误"))?;
+    let codes = new_recovery_codes(&db, user.id).await?;
+    service::mutation::Mutation::enable_totp(&db, user.id, step).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::ENABLE_MFA,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(codes)))
+}
+
+/// Replaces all recovery codes, e.g. after most of them have been used.
+pub async fn regenerate_recovery_codes(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    claims: Claims,
+    Form(form): Form<user::PasswordModel>,
+) -> Result<Json<CustomResponse<Vec<String>>>> {
+    let user = current_user(&db, &claims).await?;
+    check_password(&config, &user, &form.password)?;
+    if user.totp_enabled_at.is_none() {
+        return Err(anyhow::anyhow!("未启用两步验证").into());
+    }
+    let codes = new_recovery_codes(&db, user.id).await?;
+    Ok(Json(CustomResponse::ok(codes)))
+}
+
+pub async fn disable_totp(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    audit: Audit,
+    claims: Claims,
+    Form(form): Form<user::PasswordModel>,
+) -> Result<Json<CustomResponse<user::Profile>>> {
+    let user = current_user(&db, &claims).await?;
+    check_password(&config, &user, &form.password)?;
+    let user = service::mutation::Mutation::disable_totp(&db, user.id).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::DISABLE_MFA,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(user.into())))
+}
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+async fn new_recovery_codes(db: &DatabaseConnection, user_id: Uuid) -> Result<Vec<String>> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| service::token::recovery_code())
+        .collect();
+    let hashes = codes
+        .iter()
+        .map(|code| service::token::hash(code))
+        .collect();
+    service::mutation::Mutation::replace_recovery_codes(db, user_id, hashes).await?;
+    Ok(codes)
+}
+
+fn check_password(config: &Config, user: &user::Model, password: &str) -> Result<()> {
+    if config.password.verify(&user.password, password) == Verification::Invalid {
+        return Err(anyhow::anyhow!("当前密码不正确").into());
+    }
+    Ok(())
+}
+
+/// Stores the uploaded image like `/upload` does and sets it as the avatar.
+pub async fn upload_avatar(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<CustomResponse<user::Profile>>> {
+    let before = user::Profile::from(current_user(&db, &claims).await?);
+    let mut field = multipart
+        .next_field()
+        .await
+        .map_err(anyhow::Error::from)?
+        .ok_or_else(|| anyhow::anyhow!("未上传头像"))?;
+    // 按文件头识别格式，不信任客户端声明的类型和文件名
+    let mut head = Vec::new();
+    while head.len() < 12 {
+        match field.chunk().await.map_err(anyhow::Error::from)? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    let extension = image_extension(&head)
+        .ok_or_else(|| anyhow::anyhow!("头像必须是 PNG、JPEG、GIF 或 WebP 图片"))?;
+    let name = format!("avatar-{}.{}", Uuid::new_v4(), extension);
+    let head = stream::once(async { Ok::<_, MultipartError>(Bytes::from(head)) });
+    stream_to_file(&name, head.chain(field)).await?;
+    let user = service::mutation::Mutation::update_user_avatar(
+        &db,
+        claims.user_id,
+        format!("/uploads/{name}"),
+    )
+    .await?;
+    let after = user::Profile::from(user);
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::UPDATE_AVATAR,
+            target::USER,
+            after.id,
+            audit::diff(Some(&before), Some(&after)),
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(after)))
+}
+
+pub async fn list_access_tokens(
+    State(db): State<DatabaseConnection>,
+    claims: Claims,
+) -> Result<Json<CustomResponse<Vec<access_token::Info>>>> {
+    let tokens = service::query::Query::list_access_tokens(&db, claims.user_id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Ok(Json(CustomResponse::ok(tokens)))
+}
+
+/// Creates a personal access token. The secret is only returned here, only
+/// its hash is stored.
+pub async fn new_access_token(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    ValidatedForm(form): ValidatedForm<access_token::InsertModel>,
+) -> Result<Json<CustomResponse<access_token::Created>>> {
+    let mut scopes: Vec<&str> = vec![];
+    for name in form.scopes.split_whitespace() {
+        let scope = Scope::parse(name).ok_or_else(|| anyhow::anyhow!("未知的权限范围: {name}"))?;
+        if !scopes.contains(&scope.as_str()) {
+            scopes.push(scope.as_str());
+        }
+    }
+    if scopes.is_empty() {
+        return Err(anyhow::anyhow!("至少需要一个权限范围").into());
+    }
+    let expires_at = form
+        .expires_in_days
+        .map(|days| chrono::Utc::now().naive_utc() + chrono::Duration::days(days));
+    let token = format!("{}{}", ACCESS_TOKEN_PREFIX, service::token::generate());
+    let model = service::mutation::Mutation::create_access_token(
+        &db,
+        claims.user_id,
+        form.name,
+        service::token::hash(&token),
+        scopes.join(" "),
+        expires_at,
+    )
+    .await?;
+    let info = access_token::Info::from(model);
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::CREATE_TOKEN,
+            target::ACCESS_TOKEN,
+            info.id,
+            audit::diff(None, Some(&info)),
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(access_token::Created {
+        token,
+        info,
+    })))
+}
+
+pub async fn delete_access_token(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    claims: Claims,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<u64>>> {
+    let count = service::mutation::Mutation::delete_access_token(&db, claims.user_id, id).await?;
+    if count > 0 {
+        audit
+            .record(
+                Some(Actor::from(&claims)),
+                action::DELETE_TOKEN,
+                target::ACCESS_TOKEN,
+                id,
+                None,
+            )
+            .await?;
+    }
+    Ok(Json(CustomResponse::ok(count)))
+}
+
+/// Sends a password reset token to every account using the address.
+///
+/// The lookup and delivery run in the background and the response never
+/// depends on whether the address is known, so the endpoint cannot be used to
+/// discover accounts.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Form(form): Form<user::ForgotPasswordModel>,
+) -> Result<Json<CustomResponse<()>>> {
+    tokio::spawn(async move {
+        if let Err(err) = send_password_reset(&state, &form.email).await {
+            tracing::warn!(error = %err, "failed to send password reset mail");
+        }
+    });
+    Ok(Json(CustomResponse::ok(())))
+}
+
+async fn send_password_reset(state: &AppState, email: &str) -> Result<()> {
+    let users = service::query::Query::get_users_by_email(&state.db, email).await?;
+    for user in users {
+        let token = auth::issue_action_token(
+            &state.db,
+            &state.keys,
+            user.id,
+            user_token::purpose::RESET_PASSWORD,
+            state.config.reset_password_ttl,
+        )
+        .await?;
+        state
+            .mailer
+            .send(Mail {
+                to: user.email.clone(),
+                subject: "重置密码".to_string(),
+                body: format!(
+                    "{}，你好：\n\n请使用以下令牌调用 {}/user/password/reset 重置密码，{} 分钟内有效：\n{}\n\n如果这不是你本人的操作，请忽略本邮件。\n",
+                    user.username,
+                    state.config.public_url,
+                    state.config.reset_password_ttl / 60,
+                    token
+                ),
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Sets a new password from a reset token and logs out every session.
+pub async fn reset_password(
+    State(db): State<DatabaseConnection>,
+    State(config): State<Arc<Config>>,
+    State(keys): State<Arc<Keys>>,
+    audit: Audit,
+    ValidatedForm(form): ValidatedForm<user::ResetPasswordModel>,
+) -> Result<Json<CustomResponse<()>>> {
+    let user_id =
+        auth::consume_action_token(&db, &keys, &form.token, user_token::purpose::RESET_PASSWORD)
+            .await?;
+    let hash = config.password.hash(&form.password)?;
+    let user = service::mutation::Mutation::update_user_password(&db, user_id, hash).await?;
+    service::mutation::Mutation::revoke_user_sessions(&db, user_id).await?;
+    audit
+        .record(
+            Some(Actor::from(&user)),
+            action::RESET_PASSWORD,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(())))
+}
+
+/// Changes a user's role. Their sessions are revoked so the new role is
+/// picked up on the next login.
+pub async fn set_role(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Admin>,
+    Path(id): Path<Uuid>,
+    Form(form): Form<user::RoleModel>,
+) -> Result<Json<CustomResponse<Uuid>>> {
+    // 避免管理员误降级自己导致无人可管理
+    if claims.user_id == id {
+        return Err(CustomError::Forbidden);
+    }
+    let before = service::query::Query::get_user(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("未找到该用户"))?;
+    let user = service::mutation::Mutation::update_user_role(&db, id, form.role).await?;
+    service::mutation::Mutation::revoke_user_sessions(&db, user.id).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::SET_ROLE,
+            target::USER,
+            user.id,
+            audit::diff(
+                Some(&user::Profile::from(before)),
+                Some(&user::Profile::from(user.clone())),
+            ),
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(user.id)))
+}
+
+/// Lists and searches users, ordered by username.
+pub async fn list_users(
+    State(db): State<DatabaseConnection>,
+    Query(query): Query<user::ListQuery>,
+) -> Result<Json<CustomResponse<Page<user::Profile>>>> {
+    let (page, page_size) = page_params(query.page, query.page_size);
+    let (users, total) = service::query::Query::list_users(&db, query, page, page_size).await?;
+    Ok(Json(CustomResponse::ok(Page {
+        items: users.into_iter().map(Into::into).collect(),
+        total,
+        page,
+        page_size,
+    })))
+}
+
+/// Disables an account and logs out all of its sessions.
+pub async fn disable_user(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Admin>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<user::Profile>>> {
+    if claims.user_id == id {
+        return Err(CustomError::Forbidden);
+    }
+    let user = service::mutation::Mutation::set_user_disabled(&db, id, true).await?;
+    service::mutation::Mutation::revoke_user_sessions(&db, user.id).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::DISABLE,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(user.into())))
+}
+
+pub async fn enable_user(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Admin>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<user::Profile>>> {
+    let user = service::mutation::Mutation::set_user_disabled(&db, id, false).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::ENABLE,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(user.into())))
+}
+
+/// Deletes a user. The caller must choose whether their blogs go to another
+/// author or are removed with the account.
+pub async fn delete_user(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Admin>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<user::DeleteModel>,
+) -> Result<Json<CustomResponse<u64>>> {
+    if claims.user_id == id {
+        return Err(CustomError::Forbidden);
+    }
+    let user = service::query::Query::get_user(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("未找到该用户"))?;
+    let reassign_to = match query.blogs {
+        user::BlogDisposition::Reassign => {
+            let new_owner = query
+                .reassign_to
+                .filter(|new_owner| *new_owner != id)
+                .ok_or_else(|| anyhow::anyhow!("请指定接收文章的作者"))?;
+            service::query::Query::get_user(&db, new_owner)
+                .await?
+                .filter(|new_owner| new_owner.disabled_at.is_none())
+                .ok_or_else(|| anyhow::anyhow!("接收文章的作者不存在或已停用"))?;
+            Some(new_owner)
+        }
+        user::BlogDisposition::Remove => None,
+    };
+    let count = service::mutation::Mutation::delete_user(&db, id, reassign_to).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::DELETE_USER,
+            target::USER,
+            id,
+            audit::diff(Some(&user::Profile::from(user)), None),
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(count)))
+}
+
+/// 解除账号的登录锁定
+pub async fn unlock_user(
+    State(db): State<DatabaseConnection>,
+    audit: Audit,
+    RequireRole(claims, _): RequireRole<Admin>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomResponse<u64>>> {
+    let user = service::query::Query::get_user(&db, id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("未找到该用户"))?;
+    let count = throttle::reset(&db, &user.username).await?;
+    audit
+        .record(
+            Some(Actor::from(&claims)),
+            action::UNLOCK,
+            target::USER,
+            user.id,
+            None,
+        )
+        .await?;
+    Ok(Json(CustomResponse::ok(count)))
+}
+
+/// Public keys for verifying blog tokens in other services.
+pub async fn jwks(State(keys): State<Arc<Keys>>) -> Json<JwkSet> {
+    Json(keys.jwks().clone())
+}
+
+pub fn route(state: AppState) -> Router<AppState> {
+    let auth_route = Router::new()
+        .route("/user/logout", post(logout))
+        .route("/user/logout/all", post(logout_all))
+        .route("/user/verify/resend", post(resend_verification))
+        .route("/user/me", get(get_profile).put(update_profile))
+        .route("/user/me/password", post(change_password))
+        .route("/user/me/mfa/totp", post(enroll_totp))
+        .route("/user/me/mfa/totp/confirm", post(confirm_totp))
+        .route("/user/me/mfa/totp/disable", post(disable_totp))
+        .route(
+            "/user/me/mfa/recovery-codes",
+            post(regenerate_recovery_codes),
+        )
+        .route(
+            "/user/tokens",
+            get(list_access_tokens).post(new_access_token),
+        )
+        .route("/user/tokens/:id", delete(delete_access_token))
+        .route_layer(from_extractor_with_state::<Claims, _>(state.clone()));
+    let upload_route = Router::new()
+        .route("/user/me/avatar", post(upload_avatar))
+        .route_layer(from_extractor_with_state::<Claims, _>(state.clone()))
+        .route_layer(Extension(Scope::Upload));
+    let admin_route = Router::new()
+        .route("/user/:id/role", post(set_role))
+        .route("/user/:id/unlock", post(unlock_user))
+        .route("/user/list", get(list_users))
+        .route("/user/:id/disable", post(disable_user))
+        .route("/user/:id/enable", post(enable_user))
+        .route("/user/:id", delete(delete_user))
+        .route_layer(from_extractor_with_state::<RequireRole<Admin>, _>(state));
+    Router::new()
+        .merge(auth_route)
+        .merge(upload_route)
+        .merge(admin_route)
         .route("/user/new", post(new_user))
-        .route("/user/login", post(login_in));
-    router
+        .route("/user/login", post(login_in))
+        .route("/user/login/mfa", post(login_mfa))
+        .route("/user/refresh", post(refresh))
+        .route("/user/verify", get(verify_email))
+        .route("/user/password/forgot", post(forgot_password))
+        .route("/user/password/reset", post(reset_password))
+        .route("/.well-known/jwks.json", get(jwks))
 }
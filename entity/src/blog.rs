@@ -4,6 +4,7 @@ use super::sea_orm_active_enums::StatusEnum;
 use sea_orm::{entity::prelude::*, ActiveValue::NotSet, Set};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use validator::Validate;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "blog")]
@@ -18,6 +19,51 @@ pub struct Model {
     pub update_time: DateTime,
     pub cover_image: Option<String>,
     pub status: Option<StatusEnum>,
+    pub slug: String,
+    /// Set while the blog is in the trash.
+    pub deleted_at: Option<DateTime>,
+    /// When the blog was last submitted for review.
+    pub submitted_at: Option<DateTime>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub review_note: Option<String>,
+    /// Approved blogs stay scheduled until this time.
+    pub publish_at: Option<DateTime>,
+    /// Published blogs are taken down again at this time.
+    pub unpublish_at: Option<DateTime>,
+}
+
+/// Editorial actions moving a blog between statuses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Author hands a draft in for review.
+    Submit,
+    /// Editor publishes a pending blog, or schedules it when `publish_at`
+    /// lies in the future.
+    Approve,
+    /// Editor sends a pending blog back to its author.
+    Reject,
+}
+
+impl Transition {
+    /// Status the blog must be in, and the status it ends up in.
+    pub fn statuses(self) -> (StatusEnum, StatusEnum) {
+        match self {
+            Transition::Submit => (StatusEnum::Draft, StatusEnum::Pend),
+            Transition::Approve => (StatusEnum::Pend, StatusEnum::Post),
+            Transition::Reject => (StatusEnum::Pend, StatusEnum::Draft),
+        }
+    }
+
+    /// Why the transition is not possible from another status.
+    pub fn invalid_message(self) -> &'static str {
+        match self {
+            Transition::Submit => "Only drafts can be submitted for review",
+            Transition::Approve => "Only blogs pending review can be approved",
+            Transition::Reject => "Only blogs pending review can be rejected",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -27,10 +73,147 @@ pub struct CombineBlog {
     pub category: Option<String>,
     pub tags: Vec<String>,
 }
+/// Neighbouring published blog of a detail page.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BlogLink {
+    pub id: Uuid,
+    pub slug: String,
+    pub title: String,
+}
+
+impl From<Model> for BlogLink {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            slug: model.slug,
+            title: model.title,
+        }
+    }
+}
+
+/// A single blog with its author, category breadcrumb and neighbours.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BlogDetail {
+    #[serde(flatten)]
+    pub blog: CombineBlog,
+    pub author: Option<super::user::PublicProfile>,
+    /// Categories from the root down to the blog's own category.
+    #[schema(value_type = Vec<Object>)]
+    pub category_path: Vec<super::category::Model>,
+    pub prev: Option<BlogLink>,
+    pub next: Option<BlogLink>,
+}
+
+/// Column a blog listing is sorted by, ties are broken by id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortField {
+    #[default]
+    CreateTime,
+    UpdateTime,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Sort key of the blog a cursor points at.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum CursorKey {
+    Time(DateTime),
+    Title(String),
+}
+
+/// Position in a sorted listing, handed to clients as an opaque string.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Cursor {
+    pub sort: SortField,
+    pub order: SortOrder,
+    pub key: CursorKey,
+    pub id: Uuid,
+    /// Page backwards from this position instead of forwards.
+    pub before: bool,
+}
+
+impl Model {
+    /// Blogs without a status are drafts, the column defaults to `draft`.
+    pub fn current_status(&self) -> StatusEnum {
+        self.status.clone().unwrap_or(StatusEnum::Draft)
+    }
+
+    /// Whether the blog is public at `now`, regardless of whether the
+    /// scheduler has flipped its status yet.
+    pub fn is_published(&self, now: DateTime) -> bool {
+        matches!(self.status, Some(StatusEnum::Post | StatusEnum::Scheduled))
+            && self.publish_at.is_none_or(|at| at <= now)
+            && self.unpublish_at.is_none_or(|at| at > now)
+    }
+
+    /// When the blog went public: its scheduled time, else the approval
+    /// time, else the creation time for blogs older than the review workflow.
+    pub fn published_time(&self) -> DateTime {
+        self.publish_at
+            .or(self.reviewed_at)
+            .unwrap_or(self.create_time)
+    }
+
+    pub fn cursor(&self, sort: SortField, order: SortOrder, before: bool) -> Cursor {
+        let key = match sort {
+            SortField::CreateTime => CursorKey::Time(self.create_time),
+            SortField::UpdateTime => CursorKey::Time(self.update_time),
+            SortField::Title => CursorKey::Title(self.title.clone()),
+        };
+        Cursor {
+            sort,
+            order,
+            key,
+            id: self.id,
+            before,
+        }
+    }
+}
+
+/// Whether a blog needs any or all of the requested tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+/// Blog listing query. `cursor` takes precedence over `page`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    /// Includes blogs of all descendant categories.
+    pub category_id: Option<Uuid>,
+    /// Comma separated tag ids.
+    pub tags: Option<String>,
+    pub tag_match: Option<TagMatch>,
+    pub author_id: Option<Uuid>,
+    pub status: Option<StatusEnum>,
+    pub from: Option<DateTime>,
+    pub to: Option<DateTime>,
+    pub sort: Option<SortField>,
+    pub order: Option<SortOrder>,
+    pub cursor: Option<String>,
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReqModel {
+    /// Edits this blog like `PUT`, which requires an `If-Match` header.
     pub id: Option<String>,
+    /// Generated from the title when empty.
+    pub slug: Option<String>,
     pub title: String,
     pub content: String,
     pub category_id: Uuid,
@@ -38,9 +221,130 @@ pub struct ReqModel {
     pub tags: Vec<Uuid>,
 }
 
+/// Full replacement of an existing blog via `PUT`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateModel {
+    pub title: String,
+    pub content: String,
+    pub category_id: Uuid,
+    pub cover_image: Option<String>,
+    pub slug: Option<String>,
+    pub tags: Vec<Uuid>,
+    pub publish_at: Option<DateTime>,
+    pub unpublish_at: Option<DateTime>,
+}
+
+/// Partial update via `PATCH`, absent fields are left unchanged.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchModel {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub category_id: Option<Uuid>,
+    /// `null` removes the cover image.
+    #[serde(default, deserialize_with = "nullable")]
+    pub cover_image: Option<Option<String>>,
+    pub slug: Option<String>,
+    pub tags: Option<Vec<Uuid>>,
+    /// `null` clears the schedule; a scheduled blog goes back to draft.
+    #[serde(default, deserialize_with = "nullable")]
+    pub publish_at: Option<Option<DateTime>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub unpublish_at: Option<Option<DateTime>>,
+}
+
+impl From<UpdateModel> for PatchModel {
+    fn from(form: UpdateModel) -> Self {
+        Self {
+            title: Some(form.title),
+            content: Some(form.content),
+            category_id: Some(form.category_id),
+            cover_image: Some(form.cover_image),
+            slug: form.slug,
+            tags: Some(form.tags),
+            publish_at: Some(form.publish_at),
+            unpublish_at: Some(form.unpublish_at),
+        }
+    }
+}
+
+impl From<ReqModel> for PatchModel {
+    fn from(form: ReqModel) -> Self {
+        Self {
+            title: Some(form.title),
+            content: Some(form.content),
+            category_id: Some(form.category_id),
+            cover_image: Some(form.cover_image),
+            slug: form.slug,
+            tags: Some(form.tags),
+            publish_at: None,
+            unpublish_at: None,
+        }
+    }
+}
+
+impl PatchModel {
+    /// Columns to update, the slug and tags are handled separately.
+    pub fn changes(&self) -> ActiveModel {
+        let mut model = <ActiveModel as ActiveModelTrait>::default();
+        if let Some(title) = &self.title {
+            model.title = Set(title.clone());
+        }
+        if let Some(content) = &self.content {
+            model.content = Set(content.clone());
+        }
+        if let Some(category_id) = self.category_id {
+            model.category_id = Set(category_id);
+        }
+        if let Some(cover_image) = &self.cover_image {
+            model.cover_image = Set(cover_image.clone());
+        }
+        if let Some(publish_at) = self.publish_at {
+            model.publish_at = Set(publish_at);
+        }
+        if let Some(unpublish_at) = self.unpublish_at {
+            model.unpublish_at = Set(unpublish_at);
+        }
+        model
+    }
+}
+
+/// Tells a present `null` apart from a missing field.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct RejectModel {
+    #[validate(length(min = 1, max = 2000, message = "请填写退回原因"))]
+    pub note: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewQueueQuery {
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
+/// Trashed blogs of one author, defaults to the caller.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashQuery {
+    pub author_id: Option<Uuid>,
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct InsertModel {
     pub id: Option<String>,
+    pub slug: Option<String>,
     pub title: String,
     pub content: String,
     pub user_id: Uuid,
@@ -48,28 +352,40 @@ pub struct InsertModel {
     pub cover_image: Option<String>,
 }
 
-impl Into<ActiveModel> for InsertModel {
-    fn into(self) -> ActiveModel {
+impl From<InsertModel> for ActiveModel {
+    fn from(form: InsertModel) -> Self {
         let mut uid = NotSet;
-        if let Some(id) = self.id {
+        if let Some(id) = form.id {
             uid = Set(Uuid::parse_str(&id).unwrap());
         }
         ActiveModel {
             id: uid,
-            title: Set(self.title),
-            content: Set(self.content),
-            user_id: Set(self.user_id),
-            category_id: Set(self.category_id),
+            title: Set(form.title),
+            content: Set(form.content),
+            user_id: Set(form.user_id),
+            category_id: Set(form.category_id),
             create_time: NotSet,
             update_time: NotSet,
-            cover_image: Set(self.cover_image),
+            cover_image: Set(form.cover_image),
             status: NotSet,
+            slug: form.slug.map_or(NotSet, Set),
+            deleted_at: NotSet,
+            submitted_at: NotSet,
+            reviewed_by: NotSet,
+            reviewed_at: NotSet,
+            review_note: NotSet,
+            publish_at: NotSet,
+            unpublish_at: NotSet,
         }
     }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::blog_revision::Entity")]
+    BlogRevision,
+    #[sea_orm(has_many = "super::blog_slug_history::Entity")]
+    BlogSlugHistory,
     #[sea_orm(has_many = "super::blog_tag::Entity")]
     BlogTag,
     #[sea_orm(
@@ -90,6 +406,18 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::blog_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BlogRevision.def()
+    }
+}
+
+impl Related<super::blog_slug_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BlogSlugHistory.def()
+    }
+}
+
 impl Related<super::blog_tag::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::BlogTag.def()
@@ -37,16 +37,16 @@ pub struct InsertModel {
     pub category_id: Option<Uuid>,
 }
 
-impl Into<ActiveModel> for InsertModel {
-    fn into(self) -> ActiveModel {
+impl From<InsertModel> for ActiveModel {
+    fn from(form: InsertModel) -> Self {
         let mut uid = NotSet;
-        if let Some(id) = self.id {
+        if let Some(id) = form.id {
             uid = Set(id);
         }
         ActiveModel {
             id: uid,
-            name: Set(self.name),
-            category_id: Set(self.category_id),
+            name: Set(form.name),
+            category_id: Set(form.category_id),
         }
     }
 }
@@ -2,9 +2,19 @@
 
 pub mod prelude;
 
+pub mod access_token;
+pub mod audit_log;
 pub mod blog;
+pub mod blog_revision;
+pub mod blog_slug_history;
 pub mod blog_tag;
 pub mod category;
+pub mod login_throttle;
+pub mod oidc_login;
+pub mod recovery_code;
 pub mod sea_orm_active_enums;
+pub mod session;
 pub mod tag;
 pub mod user;
+pub mod user_identity;
+pub mod user_token;
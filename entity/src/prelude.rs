@@ -1,7 +1,17 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.12
 
+pub use super::access_token::Entity as AccessToken;
+pub use super::audit_log::Entity as AuditLog;
 pub use super::blog::Entity as Blog;
+pub use super::blog_revision::Entity as BlogRevision;
+pub use super::blog_slug_history::Entity as BlogSlugHistory;
 pub use super::blog_tag::Entity as BlogTag;
 pub use super::category::Entity as Category;
+pub use super::login_throttle::Entity as LoginThrottle;
+pub use super::oidc_login::Entity as OidcLogin;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::session::Entity as Session;
 pub use super::tag::Entity as Tag;
 pub use super::user::Entity as User;
+pub use super::user_identity::Entity as UserIdentity;
+pub use super::user_token::Entity as UserToken;
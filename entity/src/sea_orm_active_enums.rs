@@ -2,6 +2,35 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Variants are declared from least to most privileged so that roles can be
+/// compared with `>=`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "role_enum")]
+pub enum RoleEnum {
+    #[sea_orm(string_value = "reader")]
+    Reader,
+    #[sea_orm(string_value = "author")]
+    Author,
+    #[sea_orm(string_value = "editor")]
+    Editor,
+    #[sea_orm(string_value = "admin")]
+    Admin,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "status_enum")]
@@ -12,4 +41,7 @@ pub enum StatusEnum {
     Pend,
     #[sea_orm(string_value = "post")]
     Post,
+    /// Approved, goes live at `publish_at`.
+    #[sea_orm(string_value = "scheduled")]
+    Scheduled,
 }
@@ -11,17 +11,17 @@ pub struct Model {
     pub name: Option<String>,
 }
 
-impl Into<ActiveModel> for InsertModel {
-    fn into(self) -> ActiveModel {
+impl From<InsertModel> for ActiveModel {
+    fn from(form: InsertModel) -> Self {
         let uid;
-        if let Some(id) = self.id {
+        if let Some(id) = form.id {
             uid = Set(id)
         } else {
             uid = NotSet
         }
         ActiveModel {
             id: uid,
-            name: Set(self.name),
+            name: Set(form.name),
         }
     }
 }
@@ -1,7 +1,9 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.12
 
+use super::sea_orm_active_enums::RoleEnum;
 use sea_orm::{entity::prelude::*, ActiveValue::NotSet, Set};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -14,6 +16,92 @@ pub struct Model {
     pub email: String,
     #[sea_orm(column_type = "Text", nullable)]
     pub avatar: Option<String>,
+    pub role: RoleEnum,
+    pub email_verified_at: Option<DateTime>,
+    pub display_name: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub bio: Option<String>,
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTime>,
+    /// Last accepted TOTP time step, codes up to it are rejected.
+    pub totp_last_step: Option<i64>,
+    /// Disabled accounts cannot log in and their tokens are rejected.
+    pub disabled_at: Option<DateTime>,
+}
+
+/// Public view of a user. Never includes the password hash.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+    pub role: RoleEnum,
+    pub mfa_enabled: bool,
+    pub disabled: bool,
+}
+
+impl From<Model> for Profile {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            username: model.username,
+            email: model.email,
+            email_verified: model.email_verified_at.is_some(),
+            display_name: model.display_name,
+            bio: model.bio,
+            avatar: model.avatar,
+            role: model.role,
+            mfa_enabled: model.totp_enabled_at.is_some(),
+            disabled: model.disabled_at.is_some(),
+        }
+    }
+}
+
+/// What anyone may see about a blog author.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicProfile {
+    pub id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+}
+
+impl From<Model> for PublicProfile {
+    fn from(model: Model) -> Self {
+        Self {
+            id: model.id,
+            username: model.username,
+            display_name: model.display_name,
+            bio: model.bio,
+            avatar: model.avatar,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProfileModel {
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: Option<String>,
+    #[validate(length(max = 64, message = "昵称不能超过64个字符"))]
+    pub display_name: Option<String>,
+    #[validate(length(max = 500, message = "简介不能超过500个字符"))]
+    pub bio: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordModel {
+    pub current_password: String,
+    #[validate(length(min = 1, message = "密码不能为空"))]
+    pub new_password: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Validate)]
@@ -34,6 +122,97 @@ pub struct LoginModel {
     pub password: String,
 }
 
+/// Second step of a login for accounts with TOTP enabled.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MfaLoginModel {
+    pub mfa_token: String,
+    /// TOTP code or one of the recovery codes.
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TotpCodeModel {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PasswordModel {
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Query of the OpenID Connect redirect back from the provider.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OidcCallbackModel {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Admin user search, all filters optional.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    /// Matches username, email or display name.
+    pub q: Option<String>,
+    pub role: Option<RoleEnum>,
+    pub disabled: Option<bool>,
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
+/// What happens to the blogs of a deleted user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlogDisposition {
+    Reassign,
+    Remove,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteModel {
+    pub blogs: BlogDisposition,
+    /// Required when `blogs` is `reassign`.
+    pub reassign_to: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RoleModel {
+    pub role: RoleEnum,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ForgotPasswordModel {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct ResetPasswordModel {
+    pub token: String,
+    #[validate(length(min = 1, message = "密码不能为空"))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TokenModel {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshModel {
+    pub refresh_token: String,
+}
+
 impl From<InsertModel> for ActiveModel {
     fn from(i: InsertModel) -> Self {
         Self {
@@ -42,6 +221,14 @@ impl From<InsertModel> for ActiveModel {
             password: Set(i.password),
             email: Set(i.email),
             avatar: NotSet,
+            role: NotSet,
+            email_verified_at: NotSet,
+            display_name: NotSet,
+            bio: NotSet,
+            totp_secret: NotSet,
+            totp_enabled_at: NotSet,
+            totp_last_step: NotSet,
+            disabled_at: NotSet,
         }
     }
 }
@@ -50,6 +237,16 @@ impl From<InsertModel> for ActiveModel {
 pub enum Relation {
     #[sea_orm(has_many = "super::blog::Entity")]
     Blog,
+    #[sea_orm(has_many = "super::session::Entity")]
+    Session,
+    #[sea_orm(has_many = "super::user_token::Entity")]
+    UserToken,
+    #[sea_orm(has_many = "super::access_token::Entity")]
+    AccessToken,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
+    #[sea_orm(has_many = "super::user_identity::Entity")]
+    UserIdentity,
 }
 
 impl Related<super::blog::Entity> for Entity {
@@ -58,4 +255,34 @@ impl Related<super::blog::Entity> for Entity {
     }
 }
 
+impl Related<super::session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl Related<super::user_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserToken.def()
+    }
+}
+
+impl Related<super::access_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AccessToken.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
+impl Related<super::user_identity::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserIdentity.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
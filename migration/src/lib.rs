@@ -1,12 +1,46 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20240701_000001_create_session_table;
+mod m20240701_000002_add_user_role;
+mod m20240701_000003_create_user_token_table;
+mod m20240701_000004_add_user_profile;
+mod m20240701_000005_create_access_token_table;
+mod m20240701_000006_create_login_throttle_table;
+mod m20240701_000007_add_user_totp;
+mod m20240701_000008_create_user_identity_table;
+mod m20240701_000009_create_audit_log_table;
+mod m20240701_000010_add_user_disabled_at;
+mod m20240701_000011_add_blog_list_index;
+mod m20240701_000012_add_blog_slug;
+mod m20240701_000013_add_blog_deleted_at;
+mod m20240701_000014_add_blog_review;
+mod m20240701_000015_add_blog_schedule;
+mod m20240701_000016_create_blog_revision_table;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20240701_000001_create_session_table::Migration),
+            Box::new(m20240701_000002_add_user_role::Migration),
+            Box::new(m20240701_000003_create_user_token_table::Migration),
+            Box::new(m20240701_000004_add_user_profile::Migration),
+            Box::new(m20240701_000005_create_access_token_table::Migration),
+            Box::new(m20240701_000006_create_login_throttle_table::Migration),
+            Box::new(m20240701_000007_add_user_totp::Migration),
+            Box::new(m20240701_000008_create_user_identity_table::Migration),
+            Box::new(m20240701_000009_create_audit_log_table::Migration),
+            Box::new(m20240701_000010_add_user_disabled_at::Migration),
+            Box::new(m20240701_000011_add_blog_list_index::Migration),
+            Box::new(m20240701_000012_add_blog_slug::Migration),
+            Box::new(m20240701_000013_add_blog_deleted_at::Migration),
+            Box::new(m20240701_000014_add_blog_review::Migration),
+            Box::new(m20240701_000015_add_blog_schedule::Migration),
+            Box::new(m20240701_000016_create_blog_revision_table::Migration),
+        ]
     }
 }
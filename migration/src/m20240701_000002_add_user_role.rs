@@ -0,0 +1,64 @@
+use extension::postgres::Type;
+use sea_orm::{EnumIter, Iterable};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(RoleEnum)
+                    .values(RoleVariants::iter())
+                    .to_owned(),
+            )
+            .await?;
+        // 已有用户都能发布文章，默认保持作者权限
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(
+                        ColumnDef::new(User::Role)
+                            .enumeration(Alias::new("role_enum"), RoleVariants::iter())
+                            .not_null()
+                            .default(RoleVariants::Author.to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::Role)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_type(Type::drop().if_exists().name(RoleEnum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Role,
+}
+
+#[derive(DeriveIden)]
+struct RoleEnum;
+
+#[derive(DeriveIden, EnumIter)]
+enum RoleVariants {
+    Admin,
+    Editor,
+    Author,
+    Reader,
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // actor_id 不加外键，删除用户后仍保留其操作记录
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .uuid()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(AuditLog::ActorId).uuid())
+                    .col(ColumnDef::new(AuditLog::ActorName).string())
+                    .col(ColumnDef::new(AuditLog::Action).string().not_null())
+                    .col(ColumnDef::new(AuditLog::TargetType).string().not_null())
+                    .col(ColumnDef::new(AuditLog::TargetId).string())
+                    .col(ColumnDef::new(AuditLog::Ip).string())
+                    .col(ColumnDef::new(AuditLog::UserAgent).text())
+                    .col(ColumnDef::new(AuditLog::Diff).json_binary())
+                    .col(
+                        ColumnDef::new(AuditLog::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT NOW()"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        for (name, col) in [
+            ("idx-audit_log-created_at", AuditLog::CreatedAt),
+            ("idx-audit_log-actor_id", AuditLog::ActorId),
+            ("idx-audit_log-target_id", AuditLog::TargetId),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(AuditLog::Table)
+                        .col(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        // 审计记录只允许追加
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+               BEGIN
+                   RAISE EXCEPTION 'audit_log is append-only';
+               END;
+               $$ LANGUAGE plpgsql"#,
+        )
+        .await?;
+        db.execute_unprepared(
+            r#"CREATE TRIGGER audit_log_append_only
+               BEFORE UPDATE OR DELETE ON audit_log
+               FOR EACH ROW EXECUTE FUNCTION audit_log_append_only()"#,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).if_exists().to_owned())
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared("DROP FUNCTION IF EXISTS audit_log_append_only()")
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    ActorId,
+    ActorName,
+    Action,
+    TargetType,
+    TargetId,
+    Ip,
+    UserAgent,
+    Diff,
+    CreatedAt,
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+/// 文章列表按 (排序列, id) 做游标分页
+const INDEXES: [(&str, Blog); 3] = [
+    ("idx-blog-create_time-id", Blog::CreateTime),
+    ("idx-blog-update_time-id", Blog::UpdateTime),
+    ("idx-blog-title-id", Blog::Title),
+];
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, col) in INDEXES {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Blog::Table)
+                        .col(col)
+                        .col(Blog::Id)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, _) in INDEXES {
+            manager
+                .drop_index(Index::drop().name(name).table(Blog::Table).to_owned())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Blog {
+    Table,
+    Id,
+    Title,
+    CreateTime,
+    UpdateTime,
+}
@@ -0,0 +1,102 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blog::Table)
+                    .add_column(ColumnDef::new(Blog::Slug).string())
+                    .to_owned(),
+            )
+            .await?;
+        // 已有文章用ID作为链接，编辑时可以改成可读的链接
+        manager
+            .get_connection()
+            .execute_unprepared(r#"UPDATE "blog" SET "slug" = "id"::text WHERE "slug" IS NULL"#)
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blog::Table)
+                    .modify_column(ColumnDef::new(Blog::Slug).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-blog-slug")
+                    .table(Blog::Table)
+                    .col(Blog::Slug)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(BlogSlugHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BlogSlugHistory::Slug)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BlogSlugHistory::BlogId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(BlogSlugHistory::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT NOW()"),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-blog_slug_history-blog_id")
+                            .from(BlogSlugHistory::Table, BlogSlugHistory::BlogId)
+                            .to(Blog::Table, Blog::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(BlogSlugHistory::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blog::Table)
+                    .drop_column(Blog::Slug)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Blog {
+    Table,
+    Id,
+    Slug,
+}
+
+#[derive(DeriveIden)]
+enum BlogSlugHistory {
+    Table,
+    Slug,
+    BlogId,
+    CreatedAt,
+}
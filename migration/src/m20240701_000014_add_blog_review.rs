@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blog::Table)
+                    .add_column(ColumnDef::new(Blog::SubmittedAt).timestamp())
+                    .add_column(ColumnDef::new(Blog::ReviewedBy).uuid())
+                    .add_column(ColumnDef::new(Blog::ReviewedAt).timestamp())
+                    .add_column(ColumnDef::new(Blog::ReviewNote).text())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-blog-reviewed_by")
+                            .from_tbl(Blog::Table)
+                            .from_col(Blog::ReviewedBy)
+                            .to_tbl(User::Table)
+                            .to_col(User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-blog-status-submitted_at")
+                    .table(Blog::Table)
+                    .col(Blog::Status)
+                    .col(Blog::SubmittedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blog::Table)
+                    .drop_foreign_key(Alias::new("fk-blog-reviewed_by"))
+                    .drop_column(Blog::SubmittedAt)
+                    .drop_column(Blog::ReviewedBy)
+                    .drop_column(Blog::ReviewedAt)
+                    .drop_column(Blog::ReviewNote)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Blog {
+    Table,
+    Status,
+    SubmittedAt,
+    ReviewedBy,
+    ReviewedAt,
+    ReviewNote,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
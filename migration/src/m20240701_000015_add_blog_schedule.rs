@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已审核通过、等待定时发布的文章
+        manager
+            .get_connection()
+            .execute_unprepared("ALTER TYPE status_enum ADD VALUE IF NOT EXISTS 'scheduled'")
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blog::Table)
+                    .add_column(ColumnDef::new(Blog::PublishAt).timestamp())
+                    .add_column(ColumnDef::new(Blog::UnpublishAt).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+        for (name, col) in [
+            ("idx-blog-publish_at", Blog::PublishAt),
+            ("idx-blog-unpublish_at", Blog::UnpublishAt),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .name(name)
+                        .table(Blog::Table)
+                        .col(col)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type, `scheduled` is kept
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE "blog" SET "status" = 'pend' WHERE "status" = 'scheduled'"#,
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Blog::Table)
+                    .drop_column(Blog::PublishAt)
+                    .drop_column(Blog::UnpublishAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Blog {
+    Table,
+    PublishAt,
+    UnpublishAt,
+}
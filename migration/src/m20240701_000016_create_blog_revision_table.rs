@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BlogRevision::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BlogRevision::Id)
+                            .uuid()
+                            .primary_key()
+                            .extra("DEFAULT uuid_generate_v4()"),
+                    )
+                    .col(ColumnDef::new(BlogRevision::BlogId).uuid().not_null())
+                    .col(ColumnDef::new(BlogRevision::AuthorId).uuid())
+                    .col(ColumnDef::new(BlogRevision::Title).string().not_null())
+                    .col(ColumnDef::new(BlogRevision::Content).text().not_null())
+                    .col(ColumnDef::new(BlogRevision::CategoryId).uuid().not_null())
+                    .col(ColumnDef::new(BlogRevision::Tags).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(BlogRevision::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .extra("DEFAULT NOW()"),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-blog_revision-blog_id")
+                            .from(BlogRevision::Table, BlogRevision::BlogId)
+                            .to(Blog::Table, Blog::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-blog_revision-author_id")
+                            .from(BlogRevision::Table, BlogRevision::AuthorId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-blog_revision-blog_id-created_at")
+                    .table(BlogRevision::Table)
+                    .col(BlogRevision::BlogId)
+                    .col(BlogRevision::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        // 已有文章以当前内容作为第一个版本，否则首次编辑后原文就找不回来了
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"INSERT INTO "blog_revision" ("blog_id", "author_id", "title", "content", "category_id", "tags", "created_at")
+                SELECT "blog"."id", "blog"."user_id", "blog"."title", "blog"."content", "blog"."category_id",
+                    COALESCE(
+                        (SELECT jsonb_agg("tag_id" ORDER BY "tag_id") FROM "blog_tag" WHERE "blog_tag"."blog_id" = "blog"."id"),
+                        '[]'::jsonb
+                    ),
+                    "blog"."update_time"
+                FROM "blog""#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(BlogRevision::Table)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BlogRevision {
+    Table,
+    Id,
+    BlogId,
+    AuthorId,
+    Title,
+    Content,
+    CategoryId,
+    Tags,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Blog {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
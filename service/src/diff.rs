@@ -0,0 +1,126 @@
+use ::entity::blog_revision::{self, Change, ChangeTag, DiffMode, RevisionDiff};
+use similar::{ChangeTag as Tag, TextDiff};
+
+/// 按行或按词比较两段文本，相邻的同类变更合并为一段
+pub fn text(old: &str, new: &str, mode: DiffMode) -> Vec<Change> {
+    let diff = match mode {
+        DiffMode::Line => TextDiff::from_lines(old, new),
+        DiffMode::Word => TextDiff::from_unicode_words(old, new),
+    };
+    let mut changes: Vec<Change> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let tag = match change.tag() {
+            Tag::Equal => ChangeTag::Equal,
+            Tag::Insert => ChangeTag::Insert,
+            Tag::Delete => ChangeTag::Delete,
+        };
+        match changes.last_mut() {
+            Some(last) if last.tag == tag => last.value.push_str(change.value()),
+            _ => changes.push(Change {
+                tag,
+                value: change.value().to_string(),
+            }),
+        }
+    }
+    changes
+}
+
+/// 从 `from` 到 `to` 两个版本之间的差异
+pub fn revisions(
+    from: &blog_revision::Model,
+    to: &blog_revision::Model,
+    mode: DiffMode,
+) -> RevisionDiff {
+    let (old_tags, new_tags) = (from.tag_ids(), to.tag_ids());
+    RevisionDiff {
+        from: from.id,
+        to: to.id,
+        mode,
+        title: text(&from.title, &to.title, mode),
+        content: text(&from.content, &to.content, mode),
+        tags_added: new_tags
+            .iter()
+            .filter(|i| !old_tags.contains(i))
+            .copied()
+            .collect(),
+        tags_removed: old_tags
+            .iter()
+            .filter(|i| !new_tags.contains(i))
+            .copied()
+            .collect(),
+        category_changed: from.category_id != to.category_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(changes: Vec<Change>) -> Vec<(ChangeTag, String)> {
+        changes.into_iter().map(|c| (c.tag, c.value)).collect()
+    }
+
+    fn expect(list: &[(ChangeTag, &str)]) -> Vec<(ChangeTag, String)> {
+        list.iter().map(|(tag, v)| (*tag, v.to_string())).collect()
+    }
+
+    #[test]
+    fn line_mode_compares_whole_lines() {
+        let changes = text("a\nb\nc\n", "a\nB\nc\nd\n", DiffMode::Line);
+        assert_eq!(
+            parts(changes),
+            expect(&[
+                (ChangeTag::Equal, "a\n"),
+                (ChangeTag::Delete, "b\n"),
+                (ChangeTag::Insert, "B\n"),
+                (ChangeTag::Equal, "c\n"),
+                (ChangeTag::Insert, "d\n"),
+            ])
+        );
+    }
+
+    #[test]
+    fn word_mode_compares_words() {
+        let changes = text("hello big world", "hello small world", DiffMode::Word);
+        assert_eq!(
+            parts(changes),
+            expect(&[
+                (ChangeTag::Equal, "hello "),
+                (ChangeTag::Delete, "big"),
+                (ChangeTag::Insert, "small"),
+                (ChangeTag::Equal, " world"),
+            ])
+        );
+    }
+
+    #[test]
+    fn word_mode_splits_chinese_per_character() {
+        let changes = text(
+            "今天天气很好，我们去公园。",
+            "今天天气不好，我们去图书馆。",
+            DiffMode::Word,
+        );
+        assert_eq!(
+            parts(changes),
+            expect(&[
+                (ChangeTag::Equal, "今天天气"),
+                (ChangeTag::Delete, "很"),
+                (ChangeTag::Insert, "不"),
+                (ChangeTag::Equal, "好，我们去"),
+                (ChangeTag::Delete, "公园"),
+                (ChangeTag::Insert, "图书馆"),
+                (ChangeTag::Equal, "。"),
+            ])
+        );
+    }
+
+    #[test]
+    fn identical_text_is_a_single_equal_run() {
+        let changes = text("same\ntext\n", "same\ntext\n", DiffMode::Line);
+        assert_eq!(
+            parts(changes),
+            expect(&[(ChangeTag::Equal, "same\ntext\n")])
+        );
+        assert!(text("", "", DiffMode::Word).is_empty());
+    }
+}
@@ -1,3 +1,8 @@
+pub mod diff;
 pub mod mutation;
+pub mod password;
 pub mod query;
+pub mod slug;
+pub mod token;
+pub mod totp;
 pub use sea_orm;
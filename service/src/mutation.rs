@@ -1,7 +1,20 @@
-use ::entity::{blog, blog_tag, category, tag, user};
-use sea_orm::*;
+use ::entity::{
+    access_token, audit_log, blog,
+    blog::Transition,
+    blog_revision, blog_slug_history, blog_tag, category, login_throttle, oidc_login,
+    recovery_code,
+    sea_orm_active_enums::{RoleEnum, StatusEnum},
+    session, tag, user, user_identity, user_token,
+};
+use sea_orm::{
+    prelude::*,
+    sea_query::{Expr, IntoCondition},
+    *,
+};
 use uuid::Uuid;
 
+use crate::password::{PasswordConfig, Verification};
+
 pub struct Mutation {}
 
 impl Mutation {
@@ -11,11 +24,323 @@ impl Mutation {
     ) -> Result<blog::ActiveModel, DbErr> {
         form_data.save(db).await
     }
+    /// 仅当文章的 update_time 仍为 `version` 时才更新，否则返回 `None`。
+    /// 修改与 `author_id` 的新版本记录在同一事务中写入
+    pub async fn update_blog(
+        db: &DbConn,
+        id: Uuid,
+        version: DateTime,
+        mut changes: blog::ActiveModel,
+        tags: Option<&[Uuid]>,
+        author_id: Uuid,
+        force_revision: bool,
+    ) -> Result<Option<blog::Model>, DbErr> {
+        let txn = db.begin().await?;
+        changes.update_time = Set(chrono::Utc::now().naive_utc());
+        let clears_schedule = matches!(changes.publish_at, ActiveValue::Set(None));
+        let mut update = blog::Entity::update_many().set(changes);
+        // 取消定时后发布任务不会再处理该文章，退回草稿重新走审核
+        if clears_schedule {
+            update = update.col_expr(
+                blog::Column::Status,
+                Expr::cust(
+                    r#"CASE WHEN "status" = 'scheduled' THEN CAST('draft' AS status_enum) ELSE "status" END"#,
+                ),
+            );
+        }
+        let blog = update
+            .filter(blog::Column::Id.eq(id))
+            .filter(blog::Column::UpdateTime.eq(version))
+            .filter(blog::Column::DeletedAt.is_null())
+            .exec_with_returning(&txn)
+            .await?
+            .pop();
+        let Some(blog) = blog else {
+            return Ok(None);
+        };
+        if let Some(tags) = tags {
+            blog_tag::Entity::delete_many()
+                .filter(blog_tag::Column::BlogId.eq(id))
+                .filter(blog_tag::Column::TagId.is_not_in(tags.iter().copied()))
+                .exec(&txn)
+                .await?;
+            let rows = tags.iter().map(|tag_id| blog_tag::ActiveModel {
+                blog_id: Set(id),
+                tag_id: Set(*tag_id),
+            });
+            blog_tag::Entity::insert_many(rows)
+                .on_conflict(
+                    sea_query::OnConflict::columns([
+                        blog_tag::Column::BlogId,
+                        blog_tag::Column::TagId,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .do_nothing()
+                .exec(&txn)
+                .await?;
+        }
+        Self::record_revision(&txn, &blog, author_id, force_revision).await?;
+        txn.commit().await?;
+        Ok(Some(blog))
+    }
+    /// 记录文章当前的标题、内容、分类和标签。与最新版本相同时不重复记录，
+    /// 除非 `force` 为真（回滚总会产生新版本）
+    pub async fn record_revision<C: ConnectionTrait>(
+        db: &C,
+        blog: &blog::Model,
+        author_id: Uuid,
+        force: bool,
+    ) -> Result<Option<blog_revision::Model>, DbErr> {
+        let mut tags: Vec<Uuid> = blog_tag::Entity::find()
+            .filter(blog_tag::Column::BlogId.eq(blog.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|i| i.tag_id)
+            .collect();
+        tags.sort();
+        let latest = blog_revision::Entity::find()
+            .filter(blog_revision::Column::BlogId.eq(blog.id))
+            .order_by_desc(blog_revision::Column::CreatedAt)
+            .one(db)
+            .await?;
+        let unchanged = latest.is_some_and(|i| {
+            i.title == blog.title
+                && i.content == blog.content
+                && i.category_id == blog.category_id
+                && i.tag_ids() == tags
+        });
+        if unchanged && !force {
+            return Ok(None);
+        }
+        blog_revision::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            blog_id: Set(blog.id),
+            author_id: Set(Some(author_id)),
+            title: Set(blog.title.clone()),
+            content: Set(blog.content.clone()),
+            category_id: Set(blog.category_id),
+            tags: Set(Json::Array(
+                tags.iter().map(|i| Json::String(i.to_string())).collect(),
+            )),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        }
+        .insert(db)
+        .await
+        .map(Some)
+    }
+    /// 按编辑流程修改文章状态，文章已不在 `transition` 的起始状态时返回 `None`
+    pub async fn transition_blog(
+        db: &DbConn,
+        id: Uuid,
+        transition: Transition,
+        actor: Uuid,
+        note: Option<String>,
+    ) -> Result<Option<blog::Model>, DbErr> {
+        let (from, to) = transition.statuses();
+        let now = chrono::Utc::now().naive_utc();
+        let mut changes = blog::ActiveModel {
+            update_time: Set(now),
+            ..Default::default()
+        };
+        // 发布时间未到的文章先进入定时发布状态
+        let status = if transition == Transition::Approve {
+            Expr::cust_with_values(
+                r#"CASE WHEN "publish_at" > $1 THEN CAST('scheduled' AS status_enum) ELSE CAST('post' AS status_enum) END"#,
+                [now],
+            )
+        } else {
+            Expr::cust_with_values("CAST($1 AS status_enum)", [to.to_value()])
+        };
+        match transition {
+            Transition::Submit => changes.submitted_at = Set(Some(now)),
+            Transition::Approve | Transition::Reject => {
+                changes.reviewed_by = Set(Some(actor));
+                changes.reviewed_at = Set(Some(now));
+                changes.review_note = Set(note);
+            }
+        }
+        // 状态列为空的旧文章视为草稿
+        let mut current = Condition::any().add(blog::Column::Status.eq(from.clone()));
+        if from == StatusEnum::Draft {
+            current = current.add(blog::Column::Status.is_null());
+        }
+        let blog = blog::Entity::update_many()
+            .set(changes)
+            .col_expr(blog::Column::Status, status)
+            .filter(blog::Column::Id.eq(id))
+            .filter(current)
+            .filter(blog::Column::DeletedAt.is_null())
+            .exec_with_returning(db)
+            .await?
+            .pop();
+        Ok(blog)
+    }
+    /// 发布到期的定时文章。条件更新保证多个实例同时运行时每篇只发布一次
+    pub async fn publish_due_blogs(db: &DbConn, now: DateTime) -> Result<Vec<blog::Model>, DbErr> {
+        blog::Entity::update_many()
+            .set(blog::ActiveModel {
+                status: Set(Some(StatusEnum::Post)),
+                update_time: Set(now),
+                ..Default::default()
+            })
+            .filter(blog::Column::Status.eq(StatusEnum::Scheduled))
+            .filter(blog::Column::PublishAt.lte(now))
+            .filter(blog::Column::DeletedAt.is_null())
+            .exec_with_returning(db)
+            .await
+    }
+    /// 下线到期的文章，退回草稿
+    pub async fn unpublish_expired_blogs(
+        db: &DbConn,
+        now: DateTime,
+    ) -> Result<Vec<blog::Model>, DbErr> {
+        blog::Entity::update_many()
+            .set(blog::ActiveModel {
+                status: Set(Some(StatusEnum::Draft)),
+                update_time: Set(now),
+                ..Default::default()
+            })
+            .filter(blog::Column::Status.is_in([StatusEnum::Post, StatusEnum::Scheduled]))
+            .filter(blog::Column::UnpublishAt.lte(now))
+            .filter(blog::Column::DeletedAt.is_null())
+            .exec_with_returning(db)
+            .await
+    }
+    /// 移入回收站，文章已在回收站中时返回 `None`
+    pub async fn trash_blog(db: &DbConn, id: Uuid) -> Result<Option<blog::Model>, DbErr> {
+        let blog = blog::Entity::update_many()
+            .col_expr(
+                blog::Column::DeletedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(blog::Column::Id.eq(id))
+            .filter(blog::Column::DeletedAt.is_null())
+            .exec_with_returning(db)
+            .await?
+            .pop();
+        Ok(blog)
+    }
+    pub async fn restore_blog(db: &DbConn, id: Uuid) -> Result<Option<blog::Model>, DbErr> {
+        let blog = blog::Entity::update_many()
+            .col_expr(
+                blog::Column::DeletedAt,
+                Expr::value(Option::<DateTime>::None),
+            )
+            .filter(blog::Column::Id.eq(id))
+            .filter(blog::Column::DeletedAt.is_not_null())
+            .exec_with_returning(db)
+            .await?
+            .pop();
+        Ok(blog)
+    }
+    /// 彻底删除回收站中的文章，返回删除的数量
+    pub async fn purge_blogs(db: &DbConn, condition: impl IntoCondition) -> Result<u64, DbErr> {
+        let txn = db.begin().await?;
+        let trashed = Condition::all()
+            .add(blog::Column::DeletedAt.is_not_null())
+            .add(condition.into_condition());
+        blog_tag::Entity::delete_many()
+            .filter(
+                blog_tag::Column::BlogId.in_subquery(
+                    blog::Entity::find()
+                        .select_only()
+                        .column(blog::Column::Id)
+                        .filter(trashed.clone())
+                        .into_query(),
+                ),
+            )
+            .exec(&txn)
+            .await?;
+        let res = blog::Entity::delete_many()
+            .filter(trashed)
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(res.rows_affected)
+    }
+    /// 修改文章链接，旧链接记入历史以便跳转
+    pub async fn change_blog_slug(
+        db: &DbConn,
+        blog_id: Uuid,
+        old_slug: &str,
+        new_slug: &str,
+    ) -> Result<blog::Model, DbErr> {
+        let txn = db.begin().await?;
+        blog_slug_history::Entity::delete_by_id(new_slug)
+            .exec(&txn)
+            .await?;
+        blog_slug_history::Entity::insert(blog_slug_history::ActiveModel {
+            slug: Set(old_slug.to_string()),
+            blog_id: Set(blog_id),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        })
+        .on_conflict(
+            sea_query::OnConflict::column(blog_slug_history::Column::Slug)
+                .update_columns([
+                    blog_slug_history::Column::BlogId,
+                    blog_slug_history::Column::CreatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(&txn)
+        .await?;
+        let blog = blog::ActiveModel {
+            id: Set(blog_id),
+            slug: Set(new_slug.to_string()),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(blog)
+    }
     pub async fn create_user(
         db: &DbConn,
-        form_data: user::ActiveModel,
+        password: &PasswordConfig,
+        mut form_data: user::InsertModel,
     ) -> Result<user::Model, DbErr> {
-        form_data.insert(db).await
+        form_data.password = password.hash(&form_data.password)?;
+        user::ActiveModel::from(form_data).insert(db).await
+    }
+    /// 校验用户名和密码，旧格式的密码在校验通过后重新哈希
+    pub async fn check_user_exist(
+        db: &DbConn,
+        password: &PasswordConfig,
+        form: user::LoginModel,
+    ) -> Result<Option<user::Model>, DbErr> {
+        let user = user::Entity::find()
+            .filter(user::Column::Username.eq(form.username))
+            .one(db)
+            .await?;
+        let Some(user) = user else {
+            password.verify_missing(&form.password);
+            return Ok(None);
+        };
+        match password.verify(&user.password, &form.password) {
+            Verification::Valid => Ok(Some(user)),
+            Verification::NeedsRehash => {
+                let hash = password.hash(&form.password)?;
+                let user = Self::update_user_password(db, user.id, hash).await?;
+                Ok(Some(user))
+            }
+            Verification::Invalid => Ok(None),
+        }
+    }
+    pub async fn update_user_password(
+        db: &DbConn,
+        id: Uuid,
+        password_hash: String,
+    ) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Set(id),
+            password: Set(password_hash),
+            ..Default::default()
+        }
+        .update(db)
+        .await
     }
     pub async fn create_category(
         db: &DbConn,
@@ -40,4 +365,547 @@ impl Mutation {
         res.unwrap().delete(db).await?;
         Ok("删除成功")
     }
+    /// Applies the fields present in `form`. Changing the email resets its
+    /// verification state.
+    /// Applies a profile change. A new email address is unverified and any
+    /// verification link sent to the previous one stops working.
+    pub async fn update_user_profile(
+        db: &DbConn,
+        id: Uuid,
+        form: user::UpdateProfileModel,
+    ) -> Result<user::Model, DbErr> {
+        let txn = db.begin().await?;
+        let mut model = user::ActiveModel {
+            id: Set(id),
+            ..Default::default()
+        };
+        if let Some(email) = form.email {
+            model.email = Set(email);
+            model.email_verified_at = Set(None);
+            user_token::Entity::update_many()
+                .col_expr(
+                    user_token::Column::UsedAt,
+                    Expr::value(chrono::Utc::now().naive_utc()),
+                )
+                .filter(user_token::Column::UserId.eq(id))
+                .filter(user_token::Column::Purpose.eq(user_token::purpose::VERIFY_EMAIL))
+                .filter(user_token::Column::UsedAt.is_null())
+                .exec(&txn)
+                .await?;
+        }
+        if let Some(display_name) = form.display_name {
+            model.display_name = Set(Some(display_name).filter(|i| !i.is_empty()));
+        }
+        if let Some(bio) = form.bio {
+            model.bio = Set(Some(bio).filter(|i| !i.is_empty()));
+        }
+        let user = model.update(&txn).await?;
+        txn.commit().await?;
+        Ok(user)
+    }
+    pub async fn update_user_avatar(
+        db: &DbConn,
+        id: Uuid,
+        avatar: String,
+    ) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Set(id),
+            avatar: Set(Some(avatar)),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+    pub async fn update_user_role(
+        db: &DbConn,
+        id: Uuid,
+        role: RoleEnum,
+    ) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Set(id),
+            role: Set(role),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+    /// Gives the user named `username` the admin role, `None` if there is no such user.
+    pub async fn promote_admin(db: &DbConn, username: &str) -> Result<Option<user::Model>, DbErr> {
+        let users = user::Entity::update_many()
+            .col_expr(user::Column::Role, RoleEnum::Admin.as_enum())
+            .filter(user::Column::Username.eq(username))
+            .exec_with_returning(db)
+            .await?;
+        Ok(users.into_iter().next())
+    }
+    pub async fn set_user_disabled(
+        db: &DbConn,
+        id: Uuid,
+        disabled: bool,
+    ) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Set(id),
+            disabled_at: Set(disabled.then(|| chrono::Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+    /// 删除用户，其文章转给 `reassign_to`，为 `None` 时一并删除。
+    /// 会话、令牌等数据随用户级联删除。
+    pub async fn delete_user(
+        db: &DbConn,
+        id: Uuid,
+        reassign_to: Option<Uuid>,
+    ) -> Result<u64, DbErr> {
+        let txn = db.begin().await?;
+        match reassign_to {
+            Some(new_owner) => {
+                blog::Entity::update_many()
+                    .col_expr(blog::Column::UserId, Expr::value(new_owner))
+                    .filter(blog::Column::UserId.eq(id))
+                    .exec(&txn)
+                    .await?;
+            }
+            None => {
+                blog_tag::Entity::delete_many()
+                    .filter(
+                        blog_tag::Column::BlogId.in_subquery(
+                            blog::Entity::find()
+                                .select_only()
+                                .column(blog::Column::Id)
+                                .filter(blog::Column::UserId.eq(id))
+                                .into_query(),
+                        ),
+                    )
+                    .exec(&txn)
+                    .await?;
+                blog::Entity::delete_many()
+                    .filter(blog::Column::UserId.eq(id))
+                    .exec(&txn)
+                    .await?;
+            }
+        }
+        let res = user::Entity::delete_by_id(id).exec(&txn).await?;
+        txn.commit().await?;
+        Ok(res.rows_affected)
+    }
+    pub async fn create_session(
+        db: &DbConn,
+        user_id: Uuid,
+        refresh_token_hash: String,
+        expires_at: DateTime,
+    ) -> Result<session::Model, DbErr> {
+        session::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            refresh_token_hash: Set(refresh_token_hash),
+            expires_at: Set(expires_at),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            revoked_at: Set(None),
+        }
+        .insert(db)
+        .await
+    }
+    /// Swaps the refresh token of a live session. Returns `false` when the
+    /// presented token is no longer the current one, e.g. it was already rotated.
+    pub async fn rotate_session(
+        db: &DbConn,
+        id: Uuid,
+        old_hash: &str,
+        new_hash: String,
+        expires_at: DateTime,
+    ) -> Result<bool, DbErr> {
+        let res = session::Entity::update_many()
+            .col_expr(session::Column::RefreshTokenHash, Expr::value(new_hash))
+            .col_expr(session::Column::ExpiresAt, Expr::value(expires_at))
+            .filter(session::Column::Id.eq(id))
+            .filter(session::Column::RefreshTokenHash.eq(old_hash))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected == 1)
+    }
+    pub async fn revoke_session(db: &DbConn, id: Uuid) -> Result<u64, DbErr> {
+        let res = session::Entity::update_many()
+            .col_expr(
+                session::Column::RevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(session::Column::Id.eq(id))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+    pub async fn revoke_user_sessions(db: &DbConn, user_id: Uuid) -> Result<u64, DbErr> {
+        let res = session::Entity::update_many()
+            .col_expr(
+                session::Column::RevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(session::Column::UserId.eq(user_id))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+    pub async fn revoke_other_sessions(
+        db: &DbConn,
+        user_id: Uuid,
+        keep: Uuid,
+    ) -> Result<u64, DbErr> {
+        let res = session::Entity::update_many()
+            .col_expr(
+                session::Column::RevokedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(session::Column::UserId.eq(user_id))
+            .filter(session::Column::Id.ne(keep))
+            .filter(session::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+    pub async fn mark_email_verified(db: &DbConn, id: Uuid) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Set(id),
+            email_verified_at: Set(Some(chrono::Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+    pub async fn create_user_token(
+        db: &DbConn,
+        user_id: Uuid,
+        purpose: &str,
+        expires_at: DateTime,
+    ) -> Result<user_token::Model, DbErr> {
+        user_token::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            purpose: Set(purpose.to_string()),
+            expires_at: Set(expires_at),
+            used_at: Set(None),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        }
+        .insert(db)
+        .await
+    }
+    /// Marks a token as used. Returns `None` if it does not exist, was issued
+    /// for another purpose, has expired or was already used.
+    pub async fn consume_user_token(
+        db: &DbConn,
+        id: Uuid,
+        purpose: &str,
+    ) -> Result<Option<user_token::Model>, DbErr> {
+        let now = chrono::Utc::now().naive_utc();
+        let tokens = user_token::Entity::update_many()
+            .col_expr(user_token::Column::UsedAt, Expr::value(now))
+            .filter(user_token::Column::Id.eq(id))
+            .filter(user_token::Column::Purpose.eq(purpose))
+            .filter(user_token::Column::UsedAt.is_null())
+            .filter(user_token::Column::ExpiresAt.gt(now))
+            .exec_with_returning(db)
+            .await?;
+        Ok(tokens.into_iter().next())
+    }
+    pub async fn create_access_token(
+        db: &DbConn,
+        user_id: Uuid,
+        name: String,
+        token_hash: String,
+        scopes: String,
+        expires_at: Option<DateTime>,
+    ) -> Result<access_token::Model, DbErr> {
+        access_token::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            name: Set(name),
+            token_hash: Set(token_hash),
+            scopes: Set(scopes),
+            expires_at: Set(expires_at),
+            last_used_at: Set(None),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        }
+        .insert(db)
+        .await
+    }
+    pub async fn touch_access_token(db: &DbConn, id: Uuid) -> Result<(), DbErr> {
+        access_token::Entity::update_many()
+            .col_expr(
+                access_token::Column::LastUsedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(access_token::Column::Id.eq(id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+    pub async fn delete_access_token(db: &DbConn, user_id: Uuid, id: Uuid) -> Result<u64, DbErr> {
+        let res = access_token::Entity::delete_many()
+            .filter(access_token::Column::Id.eq(id))
+            .filter(access_token::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+    /// 记录一次登录失败，上次失败早于 `window_start` 时重新计数
+    pub async fn record_login_failure(
+        db: &DbConn,
+        key: &str,
+        window_start: DateTime,
+    ) -> Result<login_throttle::Model, DbErr> {
+        let now = chrono::Utc::now().naive_utc();
+        let stmt = Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO login_throttle (key, failures, last_failure_at)
+               VALUES ($1, 1, $2)
+               ON CONFLICT (key) DO UPDATE SET
+                   failures = CASE WHEN login_throttle.last_failure_at < $3
+                       THEN 1 ELSE login_throttle.failures + 1 END,
+                   last_failure_at = EXCLUDED.last_failure_at
+               RETURNING *"#,
+            [key.into(), now.into(), window_start.into()],
+        );
+        login_throttle::Entity::find()
+            .from_raw_sql(stmt)
+            .one(db)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotInserted)
+    }
+    /// 原子地占用一次登录尝试：所有 `keys` 都未被锁定时将其锁定到 `until`
+    /// 并返回 `true`，任一已被锁定时不做修改并返回 `false`
+    pub async fn reserve_login(
+        db: &DbConn,
+        keys: Vec<String>,
+        until: DateTime,
+    ) -> Result<bool, DbErr> {
+        let now = chrono::Utc::now().naive_utc();
+        let txn = db.begin().await?;
+        for key in keys {
+            let stmt = Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"INSERT INTO login_throttle (key, failures, last_failure_at, locked_until)
+                   VALUES ($1, 0, $2, $3)
+                   ON CONFLICT (key) DO UPDATE SET locked_until = EXCLUDED.locked_until
+                   WHERE login_throttle.locked_until IS NULL
+                       OR login_throttle.locked_until <= $2
+                   RETURNING *"#,
+                [key.into(), now.into(), until.into()],
+            );
+            let reserved = login_throttle::Entity::find()
+                .from_raw_sql(stmt)
+                .one(&txn)
+                .await?;
+            if reserved.is_none() {
+                txn.rollback().await?;
+                return Ok(false);
+            }
+        }
+        txn.commit().await?;
+        Ok(true)
+    }
+    /// 释放 [`Mutation::reserve_login`] 的占用，没有失败记录的条目直接删除
+    pub async fn release_login(db: &DbConn, keys: Vec<String>) -> Result<(), DbErr> {
+        login_throttle::Entity::delete_many()
+            .filter(login_throttle::Column::Key.is_in(keys.clone()))
+            .filter(login_throttle::Column::Failures.eq(0))
+            .exec(db)
+            .await?;
+        login_throttle::Entity::update_many()
+            .col_expr(
+                login_throttle::Column::LockedUntil,
+                Expr::value(Option::<DateTime>::None),
+            )
+            .filter(login_throttle::Column::Key.is_in(keys))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+    pub async fn lock_login(db: &DbConn, key: &str, until: DateTime) -> Result<(), DbErr> {
+        login_throttle::Entity::update_many()
+            .col_expr(login_throttle::Column::LockedUntil, Expr::value(until))
+            .filter(login_throttle::Column::Key.eq(key))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+    pub async fn clear_login_failures(db: &DbConn, key: &str) -> Result<u64, DbErr> {
+        let res = login_throttle::Entity::delete_by_id(key.to_string())
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+    /// 保存待确认的 TOTP 密钥，确认前不会启用
+    pub async fn set_totp_secret(
+        db: &DbConn,
+        user_id: Uuid,
+        secret: String,
+    ) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Set(user_id),
+            totp_secret: Set(Some(secret)),
+            totp_enabled_at: Set(None),
+            totp_last_step: Set(None),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+    pub async fn enable_totp(db: &DbConn, user_id: Uuid, step: i64) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Set(user_id),
+            totp_enabled_at: Set(Some(chrono::Utc::now().naive_utc())),
+            totp_last_step: Set(Some(step)),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+    pub async fn disable_totp(db: &DbConn, user_id: Uuid) -> Result<user::Model, DbErr> {
+        recovery_code::Entity::delete_many()
+            .filter(recovery_code::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        user::ActiveModel {
+            id: Set(user_id),
+            totp_secret: Set(None),
+            totp_enabled_at: Set(None),
+            totp_last_step: Set(None),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+    /// 记录已使用的 TOTP 时间步，步数不大于上次时返回 false，防止验证码重放
+    pub async fn use_totp_step(db: &DbConn, user_id: Uuid, step: i64) -> Result<bool, DbErr> {
+        let res = user::Entity::update_many()
+            .col_expr(user::Column::TotpLastStep, Expr::value(step))
+            .filter(user::Column::Id.eq(user_id))
+            .filter(
+                Condition::any()
+                    .add(user::Column::TotpLastStep.is_null())
+                    .add(user::Column::TotpLastStep.lt(step)),
+            )
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected == 1)
+    }
+    /// 用新的恢复码替换用户现有的全部恢复码
+    pub async fn replace_recovery_codes(
+        db: &DbConn,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), DbErr> {
+        let txn = db.begin().await?;
+        recovery_code::Entity::delete_many()
+            .filter(recovery_code::Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+        let now = chrono::Utc::now().naive_utc();
+        recovery_code::Entity::insert_many(code_hashes.into_iter().map(|code_hash| {
+            recovery_code::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                user_id: Set(user_id),
+                code_hash: Set(code_hash),
+                used_at: Set(None),
+                created_at: Set(now),
+            }
+        }))
+        .exec(&txn)
+        .await?;
+        txn.commit().await
+    }
+    pub async fn consume_recovery_code(
+        db: &DbConn,
+        user_id: Uuid,
+        code_hash: &str,
+    ) -> Result<bool, DbErr> {
+        let res = recovery_code::Entity::update_many()
+            .col_expr(
+                recovery_code::Column::UsedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(recovery_code::Column::UserId.eq(user_id))
+            .filter(recovery_code::Column::CodeHash.eq(code_hash))
+            .filter(recovery_code::Column::UsedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected == 1)
+    }
+    pub async fn create_oidc_login(
+        db: &DbConn,
+        form_data: oidc_login::Model,
+    ) -> Result<oidc_login::Model, DbErr> {
+        oidc_login::ActiveModel::from(form_data).insert(db).await
+    }
+    /// 取出并删除未过期的授权请求，同一个 state 只能使用一次
+    pub async fn take_oidc_login(
+        db: &DbConn,
+        state: &str,
+    ) -> Result<Option<oidc_login::Model>, DbErr> {
+        let Some(login) = oidc_login::Entity::find_by_id(state.to_string())
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let res = oidc_login::Entity::delete_by_id(state.to_string())
+            .exec(db)
+            .await?;
+        // 顺带清理过期的请求
+        oidc_login::Entity::delete_many()
+            .filter(oidc_login::Column::ExpiresAt.lt(chrono::Utc::now().naive_utc()))
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 || login.expires_at < chrono::Utc::now().naive_utc() {
+            return Ok(None);
+        }
+        Ok(Some(login))
+    }
+    pub async fn link_identity(
+        db: &DbConn,
+        user_id: Uuid,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<user_identity::Model, DbErr> {
+        user_identity::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            issuer: Set(issuer.to_string()),
+            subject: Set(subject.to_string()),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        }
+        .insert(db)
+        .await
+    }
+    /// 为外部身份新建用户并关联
+    pub async fn create_user_with_identity(
+        db: &DbConn,
+        form_data: user::ActiveModel,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<user::Model, DbErr> {
+        let txn = db.begin().await?;
+        let user = form_data.insert(&txn).await?;
+        user_identity::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user.id),
+            issuer: Set(issuer.to_string()),
+            subject: Set(subject.to_string()),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(user)
+    }
+    pub async fn create_audit_log(
+        db: &DbConn,
+        form_data: audit_log::ActiveModel,
+    ) -> Result<audit_log::Model, DbErr> {
+        form_data.insert(db).await
+    }
 }
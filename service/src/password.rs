@@ -0,0 +1,135 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use sea_orm::DbErr;
+use subtle::ConstantTimeEq;
+
+/// Argon2id cost parameters used when hashing new passwords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordConfig {
+    /// Memory size in KiB.
+    pub m_cost: u32,
+    /// Number of iterations.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    Valid,
+    /// The password matched, but the stored value is plaintext or was hashed
+    /// with different parameters and should be replaced.
+    NeedsRehash,
+    Invalid,
+}
+
+impl PasswordConfig {
+    fn argon2(&self) -> Result<Argon2<'static>, DbErr> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, None)
+            .map_err(|e| DbErr::Custom(e.to_string()))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, DbErr> {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = self
+            .argon2()?
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| DbErr::Custom(e.to_string()))?;
+        Ok(hash.to_string())
+    }
+
+    /// 用户不存在时代替 [`Self::verify`] 调用。哈希与校验的成本相同，
+    /// 响应时间不会暴露用户名是否存在
+    pub fn verify_missing(&self, password: &str) {
+        let _ = self.hash(password);
+    }
+
+    pub fn verify(&self, stored: &str, password: &str) -> Verification {
+        let Ok(hash) = PasswordHash::new(stored) else {
+            // 旧数据以明文保存
+            return if bool::from(stored.as_bytes().ct_eq(password.as_bytes())) {
+                Verification::NeedsRehash
+            } else {
+                Verification::Invalid
+            };
+        };
+        // 使用哈希中记录的参数校验，以兼容调整前的成本参数
+        if Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_err()
+        {
+            return Verification::Invalid;
+        }
+        let outdated = hash.algorithm != Algorithm::Argon2id.ident()
+            || Params::try_from(&hash).map_or(true, |params| {
+                params.m_cost() != self.m_cost
+                    || params.t_cost() != self.t_cost
+                    || params.p_cost() != self.p_cost
+            });
+        if outdated {
+            Verification::NeedsRehash
+        } else {
+            Verification::Valid
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small costs keep the tests fast.
+    const CONFIG: PasswordConfig = PasswordConfig {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn hash_verifies_with_the_same_password_only() {
+        let hash = CONFIG.hash("secret").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert_eq!(CONFIG.verify(&hash, "secret"), Verification::Valid);
+        assert_eq!(CONFIG.verify(&hash, "Secret"), Verification::Invalid);
+    }
+
+    #[test]
+    fn plaintext_rows_are_rehashed_after_a_match() {
+        assert_eq!(CONFIG.verify("secret", "secret"), Verification::NeedsRehash);
+        assert_eq!(CONFIG.verify("secret", "other"), Verification::Invalid);
+        assert_eq!(CONFIG.verify("", "secret"), Verification::Invalid);
+    }
+
+    #[test]
+    fn hashes_with_outdated_costs_are_rehashed() {
+        let old = PasswordConfig {
+            t_cost: 2,
+            ..CONFIG
+        };
+        let hash = old.hash("secret").unwrap();
+        assert_eq!(old.verify(&hash, "secret"), Verification::Valid);
+        assert_eq!(CONFIG.verify(&hash, "secret"), Verification::NeedsRehash);
+        assert_eq!(CONFIG.verify(&hash, "other"), Verification::Invalid);
+    }
+
+    #[test]
+    fn every_hash_gets_its_own_salt() {
+        assert_ne!(
+            CONFIG.hash("secret").unwrap(),
+            CONFIG.hash("secret").unwrap()
+        );
+    }
+}
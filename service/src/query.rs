@@ -1,17 +1,210 @@
 use std::iter::zip;
 
 use ::entity::{
-    blog::{self, CombineBlog},
-    blog_tag, category, tag, user,
+    access_token, audit_log,
+    blog::{self, CombineBlog, CursorKey, SortField, SortOrder, TagMatch},
+    blog_revision, blog_slug_history, blog_tag, category, login_throttle,
+    sea_orm_active_enums::{RoleEnum, StatusEnum},
+    session, tag, user, user_identity,
+};
+use sea_orm::{
+    prelude::DateTime,
+    sea_query::{self, extension::postgres::PgExpr, Expr, Func, SelectStatement, SimpleExpr},
+    *,
 };
-use sea_orm::*;
 use uuid::Uuid;
 
 pub struct Query {}
 
+/// 当前公开的文章，定时任务尚未更新状态时也按发布与下线时间判断
+pub fn published(now: DateTime) -> Condition {
+    Condition::all()
+        .add(blog::Column::Status.is_in([StatusEnum::Post, StatusEnum::Scheduled]))
+        .add(
+            Condition::any()
+                .add(blog::Column::PublishAt.is_null())
+                .add(blog::Column::PublishAt.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(blog::Column::UnpublishAt.is_null())
+                .add(blog::Column::UnpublishAt.gt(now)),
+        )
+}
+
+/// SQL counterpart of [`blog::Model::published_time`].
+fn published_time() -> SimpleExpr {
+    Func::coalesce([
+        Expr::col(blog::Column::PublishAt).into(),
+        Expr::col(blog::Column::ReviewedAt).into(),
+        Expr::col(blog::Column::CreateTime).into(),
+    ])
+    .into()
+}
+
+/// Reusable filters of a blog listing, unset fields do not filter.
+#[derive(Debug, Clone, Default)]
+pub struct BlogFilter {
+    /// Blogs in this category or any of its descendants.
+    pub category_id: Option<Uuid>,
+    pub tags: Vec<Uuid>,
+    pub tag_match: TagMatch,
+    pub author_id: Option<Uuid>,
+    pub status: Option<StatusEnum>,
+    pub from: Option<DateTime>,
+    pub to: Option<DateTime>,
+    /// Caller the listing is built for, anonymous callers only see posted blogs.
+    pub viewer: Option<(Uuid, RoleEnum)>,
+}
+
+impl BlogFilter {
+    pub fn apply(&self, select: Select<blog::Entity>) -> Select<blog::Entity> {
+        select
+            .apply_if(self.category_id, |q, v| {
+                q.filter(Expr::cust_with_values(
+                    r#""blog"."category_id" IN (
+                        WITH RECURSIVE subtree AS (
+                            SELECT "id" FROM "category" WHERE "id" = $1
+                            UNION
+                            SELECT "category"."id" FROM "category"
+                            JOIN subtree ON "category"."category_id" = subtree."id"
+                        )
+                        SELECT "id" FROM subtree
+                    )"#,
+                    [v],
+                ))
+            })
+            .apply_if(self.tags_subquery(), |q, v| {
+                q.filter(blog::Column::Id.in_subquery(v))
+            })
+            .apply_if(self.author_id, |q, v| q.filter(blog::Column::UserId.eq(v)))
+            .apply_if(self.status.clone(), |q, v| {
+                q.filter(blog::Column::Status.eq(v))
+            })
+            .apply_if(self.from, |q, v| q.filter(blog::Column::CreateTime.gte(v)))
+            .apply_if(self.to, |q, v| q.filter(blog::Column::CreateTime.lt(v)))
+            .filter(blog::Column::DeletedAt.is_null())
+            .filter(self.visibility())
+    }
+
+    /// Ids of blogs carrying any or all of the requested tags.
+    fn tags_subquery(&self) -> Option<SelectStatement> {
+        if self.tags.is_empty() {
+            return None;
+        }
+        let mut query = sea_query::Query::select();
+        query
+            .column(blog_tag::Column::BlogId)
+            .from(blog_tag::Entity)
+            .and_where(blog_tag::Column::TagId.is_in(self.tags.iter().copied()));
+        if self.tag_match == TagMatch::All {
+            let mut tags = self.tags.clone();
+            tags.sort();
+            tags.dedup();
+            query.group_by_col(blog_tag::Column::BlogId).and_having(
+                Expr::expr(Func::count_distinct(Expr::col(blog_tag::Column::TagId)))
+                    .eq(tags.len() as i64),
+            );
+        }
+        Some(query)
+    }
+
+    /// 编辑以上可见全部文章，作者额外可见自己的未发布文章
+    fn visibility(&self) -> Condition {
+        let posted = published(chrono::Utc::now().naive_utc());
+        match self.viewer {
+            Some((_, role)) if role >= RoleEnum::Editor => Condition::all(),
+            Some((user_id, _)) => Condition::any()
+                .add(posted)
+                .add(blog::Column::UserId.eq(user_id)),
+            None => Condition::all().add(posted),
+        }
+    }
+}
+
+/// One page of blogs with cursors to the neighbouring pages.
+pub struct BlogPage {
+    pub items: Vec<CombineBlog>,
+    pub total: u64,
+    pub next: Option<blog::Cursor>,
+    pub prev: Option<blog::Cursor>,
+}
+
 impl Query {
-    pub async fn get_blog_list(db: &DbConn) -> Result<Vec<CombineBlog>, DbErr> {
-        let blogs: Vec<blog::Model> = blog::Entity::find().all(db).await?;
+    /// 按条件与排序分页查询文章，传入游标时按游标翻页，否则按页码
+    pub async fn list_blogs(
+        db: &DbConn,
+        filter: &BlogFilter,
+        sort: SortField,
+        order: SortOrder,
+        cursor: Option<&blog::Cursor>,
+        page: u64,
+        page_size: u64,
+    ) -> Result<BlogPage, DbErr> {
+        let select = filter.apply(blog::Entity::find());
+        let total = select.clone().count(db).await?;
+        let column = match sort {
+            SortField::CreateTime => blog::Column::CreateTime,
+            SortField::UpdateTime => blog::Column::UpdateTime,
+            SortField::Title => blog::Column::Title,
+        };
+        // 向前翻页时按相反顺序查询，取回后再倒转
+        let before = cursor.is_some_and(|c| c.before);
+        let ascending = (order == SortOrder::Asc) != before;
+        let direction = if ascending { Order::Asc } else { Order::Desc };
+        let mut select = select
+            .order_by(column, direction.clone())
+            .order_by(blog::Column::Id, direction);
+        select = match cursor {
+            Some(cursor) => {
+                let key: Value = match &cursor.key {
+                    CursorKey::Time(time) => (*time).into(),
+                    CursorKey::Title(title) => title.clone().into(),
+                };
+                let (after_key, after_id) = if ascending {
+                    (column.gt(key.clone()), blog::Column::Id.gt(cursor.id))
+                } else {
+                    (column.lt(key.clone()), blog::Column::Id.lt(cursor.id))
+                };
+                select.filter(
+                    Condition::any()
+                        .add(after_key)
+                        .add(Condition::all().add(column.eq(key)).add(after_id)),
+                )
+            }
+            None => select.offset(page.saturating_sub(1).saturating_mul(page_size)),
+        };
+        let mut blogs = select.limit(page_size.saturating_add(1)).all(db).await?;
+        let more = blogs.len() as u64 > page_size;
+        blogs.truncate(page_size as usize);
+        if before {
+            blogs.reverse();
+        }
+        let (has_prev, has_next) = match cursor {
+            None => (page > 1, more),
+            Some(cursor) if cursor.before => (more, true),
+            Some(_) => (true, more),
+        };
+        let next = blogs
+            .last()
+            .filter(|_| has_next)
+            .map(|i| i.cursor(sort, order, false));
+        let prev = blogs
+            .first()
+            .filter(|_| has_prev)
+            .map(|i| i.cursor(sort, order, true));
+        Ok(BlogPage {
+            items: Self::combine_blogs(db, blogs).await?,
+            total,
+            next,
+            prev,
+        })
+    }
+    /// 为文章补全分类名与标签名
+    pub async fn combine_blogs(
+        db: &DbConn,
+        blogs: Vec<blog::Model>,
+    ) -> Result<Vec<CombineBlog>, DbErr> {
         let categorys: Vec<Option<category::Model>> = blogs.load_one(category::Entity, db).await?;
         let tags: Vec<Vec<tag::Model>> = blogs
             .load_many_to_many(tag::Entity, blog_tag::Entity, db)
@@ -34,6 +227,190 @@ impl Query {
             .collect();
         Ok(list)
     }
+    /// 单篇文章详情：作者、分类路径以及按发布时间相邻的已发布文章
+    pub async fn get_blog_detail(
+        db: &DbConn,
+        blog: blog::Model,
+    ) -> Result<blog::BlogDetail, DbErr> {
+        let author = Self::get_user(db, blog.user_id).await?.map(Into::into);
+        let category_path = Self::category_path(db, blog.category_id).await?;
+        let posted =
+            published(chrono::Utc::now().naive_utc()).add(blog::Column::DeletedAt.is_null());
+        let time = blog.published_time();
+        let prev = blog::Entity::find()
+            .filter(posted.clone())
+            .filter(
+                Condition::any()
+                    .add(Expr::expr(published_time()).lt(time))
+                    .add(
+                        Condition::all()
+                            .add(Expr::expr(published_time()).eq(time))
+                            .add(blog::Column::Id.lt(blog.id)),
+                    ),
+            )
+            .order_by_desc(published_time())
+            .order_by_desc(blog::Column::Id)
+            .one(db)
+            .await?;
+        let next = blog::Entity::find()
+            .filter(posted)
+            .filter(
+                Condition::any()
+                    .add(Expr::expr(published_time()).gt(time))
+                    .add(
+                        Condition::all()
+                            .add(Expr::expr(published_time()).eq(time))
+                            .add(blog::Column::Id.gt(blog.id)),
+                    ),
+            )
+            .order_by_asc(published_time())
+            .order_by_asc(blog::Column::Id)
+            .one(db)
+            .await?;
+        let blog = Self::combine_blogs(db, vec![blog]).await?.remove(0);
+        Ok(blog::BlogDetail {
+            blog,
+            author,
+            category_path,
+            prev: prev.map(Into::into),
+            next: next.map(Into::into),
+        })
+    }
+    /// 从根分类到指定分类的路径
+    pub async fn category_path(db: &DbConn, id: Uuid) -> Result<Vec<category::Model>, DbErr> {
+        category::Entity::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"WITH RECURSIVE path AS (
+                    SELECT "id", "name", "category_id", 0 AS depth FROM "category" WHERE "id" = $1
+                    UNION ALL
+                    SELECT "category"."id", "category"."name", "category"."category_id", path.depth + 1
+                    FROM "category" JOIN path ON "category"."id" = path."category_id"
+                    WHERE path.depth < 64
+                )
+                SELECT "id", "name", "category_id" FROM path ORDER BY depth DESC"#,
+                [id.into()],
+            ))
+            .all(db)
+            .await
+    }
+    pub async fn get_blog_by_slug(db: &DbConn, slug: &str) -> Result<Option<blog::Model>, DbErr> {
+        blog::Entity::find()
+            .filter(blog::Column::Slug.eq(slug))
+            .filter(blog::Column::DeletedAt.is_null())
+            .one(db)
+            .await
+    }
+    /// 旧链接对应的文章
+    pub async fn get_slug_redirect(db: &DbConn, slug: &str) -> Result<Option<blog::Model>, DbErr> {
+        let Some(history) = blog_slug_history::Entity::find_by_id(slug).one(db).await? else {
+            return Ok(None);
+        };
+        Self::get_blog(db, history.blog_id).await
+    }
+    /// 链接是否已被其他文章使用，包括其他文章的历史链接
+    pub async fn slug_taken(db: &DbConn, slug: &str, blog_id: Option<Uuid>) -> Result<bool, DbErr> {
+        let current = blog::Entity::find()
+            .filter(blog::Column::Slug.eq(slug))
+            .apply_if(blog_id, |q, v| q.filter(blog::Column::Id.ne(v)))
+            .count(db)
+            .await?;
+        let history = blog_slug_history::Entity::find()
+            .filter(blog_slug_history::Column::Slug.eq(slug))
+            .apply_if(blog_id, |q, v| {
+                q.filter(blog_slug_history::Column::BlogId.ne(v))
+            })
+            .count(db)
+            .await?;
+        Ok(current + history > 0)
+    }
+    /// 在已占用时追加数字后缀，得到一个可用的链接
+    pub async fn unique_blog_slug(
+        db: &DbConn,
+        base: &str,
+        blog_id: Option<Uuid>,
+    ) -> Result<String, DbErr> {
+        let base = if base.is_empty() { "post" } else { base };
+        let mut slug = base.to_string();
+        let mut n = 1;
+        while Self::slug_taken(db, &slug, blog_id).await? {
+            n += 1;
+            slug = format!("{base}-{n}");
+        }
+        Ok(slug)
+    }
+    /// 回收站中的文章不会被返回
+    pub async fn get_blog(db: &DbConn, id: Uuid) -> Result<Option<blog::Model>, DbErr> {
+        blog::Entity::find_by_id(id)
+            .filter(blog::Column::DeletedAt.is_null())
+            .one(db)
+            .await
+    }
+    pub async fn get_trashed_blog(db: &DbConn, id: Uuid) -> Result<Option<blog::Model>, DbErr> {
+        blog::Entity::find_by_id(id)
+            .filter(blog::Column::DeletedAt.is_not_null())
+            .one(db)
+            .await
+    }
+    /// 待审核的文章，最早提交的在前
+    pub async fn review_queue(
+        db: &DbConn,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<CombineBlog>, u64), DbErr> {
+        let paginator = blog::Entity::find()
+            .filter(blog::Column::Status.eq(StatusEnum::Pend))
+            .filter(blog::Column::DeletedAt.is_null())
+            .order_by_asc(blog::Column::SubmittedAt)
+            .order_by_asc(blog::Column::Id)
+            .paginate(db, page_size);
+        let total = paginator.num_items().await?;
+        let blogs = paginator.fetch_page(page.saturating_sub(1)).await?;
+        Ok((Self::combine_blogs(db, blogs).await?, total))
+    }
+    /// 回收站中的文章，最近删除的在前
+    pub async fn list_trashed_blogs(
+        db: &DbConn,
+        author_id: Uuid,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<blog::Model>, u64), DbErr> {
+        let paginator = blog::Entity::find()
+            .filter(blog::Column::DeletedAt.is_not_null())
+            .filter(blog::Column::UserId.eq(author_id))
+            .order_by_desc(blog::Column::DeletedAt)
+            .order_by_desc(blog::Column::Id)
+            .paginate(db, page_size);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(page.saturating_sub(1)).await?;
+        Ok((items, total))
+    }
+    /// 文章的历史版本，最新的在前
+    pub async fn list_revisions(
+        db: &DbConn,
+        blog_id: Uuid,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<blog_revision::Model>, u64), DbErr> {
+        let paginator = blog_revision::Entity::find()
+            .filter(blog_revision::Column::BlogId.eq(blog_id))
+            .order_by_desc(blog_revision::Column::CreatedAt)
+            .order_by_desc(blog_revision::Column::Id)
+            .paginate(db, page_size);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(page.saturating_sub(1)).await?;
+        Ok((items, total))
+    }
+    pub async fn get_revision(
+        db: &DbConn,
+        blog_id: Uuid,
+        id: Uuid,
+    ) -> Result<Option<blog_revision::Model>, DbErr> {
+        blog_revision::Entity::find_by_id(id)
+            .filter(blog_revision::Column::BlogId.eq(blog_id))
+            .one(db)
+            .await
+    }
     pub async fn get_tag_list(db: &DbConn, id: Uuid) -> Result<Vec<tag::Model>, DbErr> {
         let tag_ids: Vec<Uuid> = blog_tag::Entity::find()
             .filter(blog_tag::Column::BlogId.eq(id))
@@ -48,16 +425,73 @@ impl Query {
             .await?;
         Ok(tags)
     }
-    pub async fn check_user_exist(
+    pub async fn get_user(db: &DbConn, id: Uuid) -> Result<Option<user::Model>, DbErr> {
+        user::Entity::find_by_id(id).one(db).await
+    }
+    /// 管理员按条件分页查询用户，返回当页记录与总数
+    pub async fn list_users(
         db: &DbConn,
-        form: user::LoginModel,
+        query: user::ListQuery,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<user::Model>, u64), DbErr> {
+        let paginator = user::Entity::find()
+            .apply_if(query.q.filter(|q| !q.is_empty()), |q, v| {
+                let escaped = v
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                let pattern = format!("%{escaped}%");
+                q.filter(
+                    Condition::any()
+                        .add(Expr::col(user::Column::Username).ilike(&pattern))
+                        .add(Expr::col(user::Column::Email).ilike(&pattern))
+                        .add(Expr::col(user::Column::DisplayName).ilike(&pattern)),
+                )
+            })
+            .apply_if(query.role, |q, v| q.filter(user::Column::Role.eq(v)))
+            .apply_if(query.disabled, |q, v| {
+                q.filter(if v {
+                    user::Column::DisabledAt.is_not_null()
+                } else {
+                    user::Column::DisabledAt.is_null()
+                })
+            })
+            .order_by_asc(user::Column::Username)
+            .paginate(db, page_size);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(page.saturating_sub(1)).await?;
+        Ok((items, total))
+    }
+    pub async fn get_user_by_username(
+        db: &DbConn,
+        username: &str,
     ) -> Result<Option<user::Model>, DbErr> {
-        let user = user::Entity::find()
-            .filter(user::Column::Username.eq(form.username))
-            .filter(user::Column::Password.eq(form.password))
+        user::Entity::find()
+            .filter(user::Column::Username.eq(username))
             .one(db)
-            .await?;
-        Ok(user)
+            .await
+    }
+    pub async fn get_user_by_identity(
+        db: &DbConn,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<user::Model>, DbErr> {
+        user::Entity::find()
+            .inner_join(user_identity::Entity)
+            .filter(user_identity::Column::Issuer.eq(issuer))
+            .filter(user_identity::Column::Subject.eq(subject))
+            .one(db)
+            .await
+    }
+    pub async fn get_users_by_email(db: &DbConn, email: &str) -> Result<Vec<user::Model>, DbErr> {
+        user::Entity::find()
+            .filter(user::Column::Email.eq(email))
+            .all(db)
+            .await
+    }
+    pub async fn get_session(db: &DbConn, id: Uuid) -> Result<Option<session::Model>, DbErr> {
+        session::Entity::find_by_id(id).one(db).await
     }
     pub async fn query_blog_category(
         db: &DbConn,
@@ -88,11 +522,30 @@ impl Query {
         let categories = Self::recur_category(first_categories, left_categories);
         Ok(categories)
     }
+    pub async fn get_access_token_by_hash(
+        db: &DbConn,
+        token_hash: &str,
+    ) -> Result<Option<access_token::Model>, DbErr> {
+        access_token::Entity::find()
+            .filter(access_token::Column::TokenHash.eq(token_hash))
+            .one(db)
+            .await
+    }
+    pub async fn list_access_tokens(
+        db: &DbConn,
+        user_id: Uuid,
+    ) -> Result<Vec<access_token::Model>, DbErr> {
+        access_token::Entity::find()
+            .filter(access_token::Column::UserId.eq(user_id))
+            .order_by_desc(access_token::Column::CreatedAt)
+            .all(db)
+            .await
+    }
     pub fn recur_category(
         mut categories: Vec<category::TreeModel>,
         left_categories: Vec<category::TreeModel>,
     ) -> Vec<category::TreeModel> {
-        if categories.len() == 0 {
+        if categories.is_empty() {
             return categories;
         }
         for node in categories.iter_mut() {
@@ -106,4 +559,45 @@ impl Query {
         }
         categories
     }
+    pub async fn get_login_throttles(
+        db: &DbConn,
+        keys: Vec<String>,
+    ) -> Result<Vec<login_throttle::Model>, DbErr> {
+        login_throttle::Entity::find()
+            .filter(login_throttle::Column::Key.is_in(keys))
+            .all(db)
+            .await
+    }
+    /// 按条件分页查询审计记录，最新的在前，返回当页记录与总数
+    pub async fn list_audit_logs(
+        db: &DbConn,
+        query: audit_log::QueryModel,
+        page: u64,
+        page_size: u64,
+    ) -> Result<(Vec<audit_log::Model>, u64), DbErr> {
+        let paginator = audit_log::Entity::find()
+            .apply_if(query.actor_id, |q, v| {
+                q.filter(audit_log::Column::ActorId.eq(v))
+            })
+            .apply_if(query.action, |q, v| {
+                q.filter(audit_log::Column::Action.eq(v))
+            })
+            .apply_if(query.target_type, |q, v| {
+                q.filter(audit_log::Column::TargetType.eq(v))
+            })
+            .apply_if(query.target_id, |q, v| {
+                q.filter(audit_log::Column::TargetId.eq(v))
+            })
+            .apply_if(query.from, |q, v| {
+                q.filter(audit_log::Column::CreatedAt.gte(v))
+            })
+            .apply_if(query.to, |q, v| {
+                q.filter(audit_log::Column::CreatedAt.lt(v))
+            })
+            .order_by_desc(audit_log::Column::CreatedAt)
+            .paginate(db, page_size);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(page.saturating_sub(1)).await?;
+        Ok((items, total))
+    }
 }
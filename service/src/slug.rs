@@ -0,0 +1,45 @@
+/// Longest generated slug, longer ones are cut at a word boundary.
+const MAX_LEN: usize = 80;
+
+/// URL slug of a title. Chinese and other non-Latin scripts are transliterated,
+/// e.g. `你好 世界` becomes `ni-hao-shi-jie`. May be empty.
+pub fn slugify(text: &str) -> String {
+    let slug = slug::slugify(text);
+    if slug.len() <= MAX_LEN {
+        return slug;
+    }
+    let cut = &slug[..MAX_LEN];
+    cut.rfind('-')
+        .map_or(cut, |end| &cut[..end])
+        .trim_end_matches('-')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transliterates_chinese() {
+        assert_eq!(slugify("你好 世界"), "ni-hao-shi-jie");
+    }
+
+    #[test]
+    fn normalizes_latin_titles() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Rust & Axum  "), "rust-axum");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn long_slugs_are_cut_at_a_word_boundary() {
+        let title = "word ".repeat(30);
+        let slug = slugify(&title);
+        assert!(slug.len() <= MAX_LEN);
+        assert!(slug.split('-').all(|part| part == "word"));
+        assert_eq!(slug.len(), 79);
+
+        let slug = slugify(&"a".repeat(100));
+        assert_eq!(slug, "a".repeat(MAX_LEN));
+    }
+}
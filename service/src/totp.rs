@@ -0,0 +1,97 @@
+//! RFC 6238 time-based one-time passwords (SHA-1, 6 digits, 30 second steps),
+//! the parameters every common authenticator app understands.
+
+use sea_orm::DbErr;
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const DIGITS: usize = 6;
+const STEP: u64 = 30;
+/// Codes from the neighbouring steps are accepted to tolerate clock drift.
+const SKEW: u64 = 1;
+
+/// New random base32 encoded secret.
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+fn totp(secret: &str, issuer: &str, account: &str) -> Result<TOTP, DbErr> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| DbErr::Custom(e.to_string()))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        SKEW as u8,
+        STEP,
+        secret,
+        Some(issuer.to_string()),
+        account.to_string(),
+    )
+    .map_err(|e| DbErr::Custom(e.to_string()))
+}
+
+/// `otpauth://` URI for enrolling the secret in an authenticator app.
+pub fn otpauth_uri(secret: &str, issuer: &str, account: &str) -> Result<String, DbErr> {
+    Ok(totp(secret, issuer, account)?.get_url())
+}
+
+/// Checks `code` at unix time `now` and returns the matching time step.
+///
+/// Steps up to `last_step` are rejected so an observed code cannot be replayed.
+pub fn verify(secret: &str, code: &str, now: u64, last_step: Option<i64>) -> Option<i64> {
+    let totp = totp(secret, "blog", "user").ok()?;
+    let current = now / STEP;
+    (current.saturating_sub(SKEW)..=current + SKEW)
+        .filter(|step| last_step.is_none_or(|last| *step as i64 > last))
+        .find(|step| {
+            totp.generate(step * STEP)
+                .as_bytes()
+                .ct_eq(code.as_bytes())
+                .into()
+        })
+        .map(|step| step as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Base32 of the RFC 6238 SHA-1 test key "12345678901234567890".
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn accepts_rfc_6238_test_vectors() {
+        assert_eq!(verify(SECRET, "287082", 59, None), Some(1));
+        assert_eq!(verify(SECRET, "081804", 1111111109, None), Some(37037036));
+        assert_eq!(verify(SECRET, "005924", 1234567890, None), Some(41152263));
+    }
+
+    #[test]
+    fn tolerates_one_step_of_clock_drift() {
+        assert_eq!(verify(SECRET, "287082", 89, None), Some(1));
+        assert_eq!(verify(SECRET, "287082", 0, None), Some(1));
+        assert_eq!(verify(SECRET, "287082", 90, None), None);
+    }
+
+    #[test]
+    fn rejects_replayed_steps() {
+        assert_eq!(verify(SECRET, "287082", 59, Some(1)), None);
+        assert_eq!(verify(SECRET, "287082", 59, Some(2)), None);
+        assert_eq!(verify(SECRET, "287082", 59, Some(0)), Some(1));
+    }
+
+    #[test]
+    fn rejects_wrong_codes_and_secrets() {
+        assert_eq!(verify(SECRET, "287083", 59, None), None);
+        assert_eq!(verify(SECRET, "", 59, None), None);
+        assert_eq!(verify("not base32!", "287082", 59, None), None);
+    }
+
+    #[test]
+    fn generated_secrets_verify_their_own_codes() {
+        let secret = generate_secret();
+        let code = totp(&secret, "blog", "user").unwrap().generate(300);
+        assert_eq!(verify(&secret, &code, 300, None), Some(10));
+    }
+}